@@ -1,14 +1,181 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    day::{d4::Template, d7::OpSet},
+    harness::params,
+};
 
 /// Run advent of code 2024 solutions
 #[derive(Debug, Parser)]
-#[command(about, long_about = None)]
+#[command(
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Day of the advent
-    #[arg(value_parser = clap::value_parser!(u8).range(1..=25))]
-    pub day: u8,
+    #[arg(required = true, value_parser = clap::value_parser!(u8).range(1..=25))]
+    pub day: Option<u8>,
 
     /// Part of the daily exercise
     #[arg(value_parser = clap::value_parser!(u8).range(1..=2), default_value_t = 1)]
     pub part: u8,
+
+    #[command(flatten)]
+    pub params: ParamArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Generate and register a skeleton solution for a new day
+    New {
+        /// Day of the advent
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=25))]
+        day: u8,
+    },
+
+    /// Visualise or analyse a day's puzzle input
+    #[command(subcommand)]
+    Inspect(Inspect),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Inspect {
+    /// Distribution of the distances between paired IDs, and unmatched IDs
+    D1 {
+        /// Number of histogram buckets
+        #[arg(long, default_value_t = 10)]
+        buckets: usize,
+
+        /// Also write the pairings to this CSV file
+        #[arg(long, value_name = "FILE")]
+        csv: Option<PathBuf>,
+    },
+
+    /// Show whether each report is safe, or which levels to remove to fix it
+    D2 {
+        /// Most levels which may be removed to make a report safe
+        #[arg(long, default_value_t = 1)]
+        tolerance: usize,
+    },
+
+    /// Trace the instructions found in memory, and whether each applied
+    D3 {
+        /// Part of the daily exercise, i.e. whether `do()`/`don't()` apply
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2), default_value_t = 2)]
+        part: u8,
+    },
+
+    /// Find words or shapes in the word search, highlighting them in the
+    /// grid
+    D4 {
+        /// Words to look for, separated by commas
+        #[arg(long, value_delimiter = ',', default_value = "XMAS")]
+        words: Vec<String>,
+
+        /// Look for this shape instead, in any orientation, as rows separated
+        /// by `/` with `.` matching anything, e.g. `M.S/.A./M.S`
+        #[arg(long, conflicts_with = "words")]
+        template: Option<Template>,
+    },
+
+    /// Check each update against the ordering rules, showing how to reorder
+    /// it and whether that order is the only one
+    D5,
+
+    /// Draw the guard's route, and explain whether an extra obstacle makes
+    /// them loop
+    D6 {
+        /// Place an extra obstacle at this position
+        #[arg(long, value_name = "X,Y", value_parser = parse_point)]
+        obstacle: Option<(usize, usize)>,
+
+        /// Write a PPM image to this path instead of drawing in the terminal
+        #[arg(long, value_name = "FILE")]
+        ppm: Option<PathBuf>,
+
+        /// Pixels per tile in the PPM image
+        #[arg(long, default_value_t = 8)]
+        scale: usize,
+    },
+
+    /// Show which operators solve each calibration equation
+    D7 {
+        /// Operators to place between terms, from `+`, `*`, `||`, `-`, `^`
+        /// (xor) and `**`
+        #[arg(long, default_value = "+,*", allow_hyphen_values = true)]
+        ops: OpSet,
+
+        /// Also count every operator assignment which solves each equation
+        #[arg(long)]
+        count: bool,
+    },
+
+    /// Trace compaction of the disk, move by move for small disks
+    D9 {
+        /// Part of the daily exercise, i.e. moving blocks or whole files
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2), default_value_t = 2)]
+        part: u8,
+
+        /// Show each move for disks up to this many blocks, otherwise only
+        /// the summary
+        #[arg(long, default_value_t = 100)]
+        max_blocks: usize,
+    },
+
+    /// Animate the robots, in the terminal or as numbered PGM frames
+    D14 {
+        /// First second to show
+        #[arg(long, default_value_t = 0)]
+        from: usize,
+
+        /// Second to stop before
+        #[arg(long, default_value_t = 100)]
+        to: usize,
+
+        /// Write frames to this directory instead of playing them
+        #[arg(long, value_name = "DIR")]
+        pgm: Option<PathBuf>,
+
+        /// Pixels per tile in exported frames
+        #[arg(long, default_value_t = 4)]
+        scale: usize,
+
+        /// Playback speed in frames per second
+        #[arg(long, default_value_t = 10)]
+        fps: u32,
+
+        /// Print the frame with the Christmas tree instead, and the second it
+        /// appears at
+        #[arg(long, conflicts_with_all = ["from", "to", "pgm"])]
+        tree: bool,
+
+        #[command(flatten)]
+        params: ParamArgs,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ParamArgs {
+    /// Override a puzzle parameter, e.g. `--param blinks=40`
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = params::parse_override)]
+    pub params: Vec<(String, String)>,
+}
+
+fn parse_point(s: &str) -> Result<(usize, usize), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y but got '{}'", s))?;
+    let coord = |c: &str| {
+        c.trim()
+            .parse()
+            .map_err(|_| format!("invalid coordinate '{}'", c))
+    };
+    Ok((coord(x)?, coord(y)?))
 }
@@ -30,11 +30,23 @@ pub mod p1 {
         }
     }
 
+    /// Both lists, sorted so that pairing them up smallest to largest is
+    /// zipping them together
     pub struct Input {
         l: Vec<u32>,
         r: Vec<u32>,
     }
 
+    impl Input {
+        pub fn left(&self) -> &[u32] {
+            &self.l
+        }
+
+        pub fn right(&self) -> &[u32] {
+            &self.r
+        }
+    }
+
     impl<'a> SolutionInput<'a> for Input {
         fn read(reader: impl std::io::BufRead + 'a) -> input::Result<Self> {
             let mut l: Vec<u32> = vec![];
@@ -55,7 +67,11 @@ pub mod p1 {
 }
 
 pub mod p2 {
-    use crate::harness::{input, iter, Result, Solution, SolutionInput};
+    use std::collections::BTreeMap;
+
+    use thiserror::Error;
+
+    use crate::harness::{input, iter, Error, Result, Solution, SolutionInput};
 
     use super::parse_line;
 
@@ -63,27 +79,44 @@ pub mod p2 {
 
     impl<'a> Solution<'a> for P2 {
         type Input = Input;
-        type Output = u32;
+        type Output = u64;
 
+        /// Each left ID contributes its value times the number of times it
+        /// appears on the right, so every pair of equal IDs across the lists
+        /// counts once
         fn solve(input: Self::Input) -> Result<Self::Output> {
-            Ok(input.r.iter().filter(|n| input.l[**n as usize]).sum())
+            input.l.iter().try_fold(0_u64, |total, (id, l_count)| {
+                let r_count = input.r.get(id).copied().unwrap_or(0);
+                (*id as u64)
+                    .checked_mul(*l_count)
+                    .and_then(|score| score.checked_mul(r_count))
+                    .and_then(|score| total.checked_add(score))
+                    .ok_or_else(|| Error::SolutionError(Box::new(OverflowError { id: *id })))
+            })
         }
     }
 
+    #[derive(Debug, Error)]
+    #[error("similarity score overflows adding ID {id}")]
+    pub struct OverflowError {
+        id: u32,
+    }
+
+    /// How many times each ID appears in each list, in ID order
     pub struct Input {
-        l: [bool; 100_000],
-        r: Vec<u32>,
+        l: BTreeMap<u32, u64>,
+        r: BTreeMap<u32, u64>,
     }
 
     impl<'a> SolutionInput<'a> for Input {
         fn read(reader: impl std::io::BufRead + 'a) -> input::Result<Self> {
-            let mut l = [false; 100_000];
-            let mut r: Vec<u32> = vec![];
+            let mut l: BTreeMap<u32, u64> = BTreeMap::new();
+            let mut r: BTreeMap<u32, u64> = BTreeMap::new();
 
             for (idx, line) in iter::lines(reader).enumerate() {
                 let (l_num, r_num) = parse_line(idx, line?)?;
-                l[l_num as usize] = true;
-                r.push(r_num);
+                *l.entry(l_num).or_default() += 1;
+                *r.entry(r_num).or_default() += 1;
             }
 
             Ok(Self { l, r })
@@ -91,6 +124,123 @@ pub mod p2 {
     }
 }
 
+/// Views of the paired up lists beyond the puzzle answers
+pub mod analysis {
+    use std::{
+        collections::BTreeSet,
+        io::{self, Write},
+        ops::RangeInclusive,
+    };
+
+    use super::p1::Input;
+
+    /// Distance between each pair, in pairing order
+    pub fn distances(input: &Input) -> Vec<u32> {
+        input
+            .left()
+            .iter()
+            .zip(input.right())
+            .map(|(l, r)| l.abs_diff(*r))
+            .collect()
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Stats {
+        pub min: u32,
+        pub max: u32,
+        /// Mean of the middle two distances when there's an even number
+        pub median: f64,
+    }
+
+    /// Summary of the distances, or `None` if there aren't any
+    pub fn stats(distances: &[u32]) -> Option<Stats> {
+        let mut sorted = distances.to_vec();
+        sorted.sort_unstable();
+
+        let mid = sorted.len() / 2;
+        let median = match sorted.len() {
+            0 => return None,
+            len if len % 2 == 0 => (sorted[mid - 1] as f64 + sorted[mid] as f64) / 2.0,
+            _ => sorted[mid] as f64,
+        };
+
+        Some(Stats {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            median,
+        })
+    }
+
+    /// Count of distances in each of up to `buckets` equal width ranges
+    /// spanning the smallest to largest distance
+    pub fn histogram(distances: &[u32], buckets: usize) -> Vec<(RangeInclusive<u32>, usize)> {
+        let (Some(min), Some(max)) = (distances.iter().min(), distances.iter().max()) else {
+            return vec![];
+        };
+        let span = (max - min) as u64 + 1;
+        let width = span.div_ceil(buckets.max(1) as u64);
+        let buckets = span.div_ceil(width) as usize;
+
+        let mut counts = vec![0; buckets];
+        for d in distances {
+            counts[((d - min) as u64 / width) as usize] += 1;
+        }
+
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(idx, count)| {
+                let lo = *min as u64 + idx as u64 * width;
+                let hi = (lo + width - 1).min(*max as u64);
+                (lo as u32..=hi as u32, count)
+            })
+            .collect()
+    }
+
+    /// Draw each bucket of a [`histogram`] as a line with its range, a bar
+    /// up to `width` wide scaled to the largest count, and its count. A
+    /// bucket with any distances always gets some bar.
+    pub fn render_histogram(histogram: &[(RangeInclusive<u32>, usize)], width: usize) -> String {
+        let most = histogram.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        let mut out = String::new();
+
+        for (range, count) in histogram {
+            let bar_len = match most {
+                0 => 0,
+                _ => (count * width).div_ceil(most),
+            };
+            out.push_str(&format!(
+                "{:>8}..={:<8} {:<width$} {}\n",
+                range.start(),
+                range.end(),
+                "#".repeat(bar_len),
+                count,
+            ));
+        }
+
+        out
+    }
+
+    /// IDs which appear in the left list but not the right, and vice versa
+    pub fn unmatched(input: &Input) -> (Vec<u32>, Vec<u32>) {
+        let l: BTreeSet<u32> = input.left().iter().copied().collect();
+        let r: BTreeSet<u32> = input.right().iter().copied().collect();
+        (
+            l.difference(&r).copied().collect(),
+            r.difference(&l).copied().collect(),
+        )
+    }
+
+    /// Write the pairings as CSV, with a header row
+    pub fn write_csv(input: &Input, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "rank,left,right,distance")?;
+        for (rank, (l, r)) in input.left().iter().zip(input.right()).enumerate() {
+            writeln!(w, "{},{},{},{}", rank + 1, l, r, l.abs_diff(*r))?;
+        }
+        w.flush()
+    }
+}
+
 fn parse_line(line_num: usize, line: String) -> input::Result<(u32, u32)> {
     let mut nums = line.split_whitespace();
     let l_num = parse_num(line_num, nums.next())?;
@@ -106,3 +256,106 @@ fn parse_num(line_num: usize, num: Option<&str>) -> input::Result<u32> {
 
     parse::parse_u32(num)
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        day_test,
+        harness::{Day, Params, Part, SolutionInput},
+    };
+
+    use super::{analysis, p1, D1};
+
+    const EXAMPLE: &str = "\
+3   4
+4   3
+2   5
+1   3
+3   9
+3   3
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D1, Part::One, EXAMPLE, 11);
+        day_test!(D1, Part::Two, EXAMPLE, 31);
+    }
+
+    #[test]
+    fn test_similarity_large_and_duplicate_ids() {
+        // each 4000000000 on the left pairs with all three on the right
+        day_test!(
+            D1,
+            Part::Two,
+            "4000000000 4000000000\n4000000000 4000000000\n7 4000000000\n",
+            24_000_000_000_u64
+        );
+        day_test!(D1, Part::Two, "100000 1\n2 3\n", 0);
+    }
+
+    #[test]
+    fn test_similarity_overflow() {
+        // 4000000000 * 70000 * 70000 doesn't fit in a u64
+        let input = "4000000000 4000000000\n".repeat(70_000);
+        let err = D1::run_with(Part::Two, input.as_bytes(), &Params::new()).unwrap_err();
+        assert_eq!(
+            "similarity score overflows adding ID 4000000000",
+            err.to_string()
+        );
+
+        // IDs are added in order, so the lowest overflowing one is reported
+        let input = "4000000000 4000000000\n3900000000 3900000000\n".repeat(70_000);
+        let err = D1::run_with(Part::Two, input.as_bytes(), &Params::new()).unwrap_err();
+        assert_eq!(
+            "similarity score overflows adding ID 3900000000",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_analysis() {
+        let input = p1::Input::read(EXAMPLE.as_bytes()).unwrap();
+        let distances = analysis::distances(&input);
+        assert_eq!(vec![2, 1, 0, 1, 2, 5], distances);
+
+        let stats = analysis::stats(&distances).unwrap();
+        assert_eq!((0, 5, 1.5), (stats.min, stats.max, stats.median));
+        assert_eq!(None, analysis::stats(&[]));
+
+        assert_eq!(
+            vec![(0..=1, 3), (2..=3, 2), (4..=5, 1)],
+            analysis::histogram(&distances, 3)
+        );
+        assert_eq!(
+            vec![
+                (0..=0, 1),
+                (1..=1, 2),
+                (2..=2, 2),
+                (3..=3, 0),
+                (4..=4, 0),
+                (5..=5, 1)
+            ],
+            analysis::histogram(&distances, 10)
+        );
+        assert_eq!(vec![(7..=7, 2)], analysis::histogram(&[7, 7], 4));
+
+        assert_eq!(
+            "       0..=1        ###### 3\n       2..=3        ####   2\n       4..=5        ##     1\n",
+            analysis::render_histogram(&analysis::histogram(&distances, 3), 6)
+        );
+        assert_eq!(
+            "       0..=9             0\n      10..=19            0\n",
+            analysis::render_histogram(&[(0..=9, 0), (10..=19, 0)], 4)
+        );
+        assert_eq!("", analysis::render_histogram(&[], 4));
+
+        assert_eq!((vec![1, 2], vec![5, 9]), analysis::unmatched(&input));
+
+        let mut csv = vec![];
+        analysis::write_csv(&input, &mut csv).unwrap();
+        assert_eq!(
+            "rank,left,right,distance\n1,1,3,2\n2,2,3,1\n3,3,3,0\n4,3,4,1\n5,3,5,2\n6,4,9,5\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+}
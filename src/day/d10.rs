@@ -55,13 +55,13 @@ impl Map {
             visited.insert((row, col));
 
             let mut neighbors = vec![];
-            if (row as isize) - 1 >= 0 {
+            if row > 0 {
                 neighbors.push((row - 1, col));
             }
             if row + 1 < self.rows() {
                 neighbors.push((row + 1, col));
             }
-            if (col as isize) - 1 >= 0 {
+            if col > 0 {
                 neighbors.push((row, col - 1));
             }
             if col + 1 < self.cols() {
@@ -90,13 +90,13 @@ impl Map {
             }
 
             let mut neighbors = vec![];
-            if (row as isize) - 1 >= 0 {
+            if row > 0 {
                 neighbors.push((row - 1, col));
             }
             if row + 1 < self.rows() {
                 neighbors.push((row + 1, col));
             }
-            if (col as isize) - 1 >= 0 {
+            if col > 0 {
                 neighbors.push((row, col - 1));
             }
             if col + 1 < self.cols() {
@@ -117,7 +117,7 @@ impl Map {
     }
 
     pub fn cols(&self) -> usize {
-        self.tiles.get(0).map(|row| row.len()).unwrap_or(0)
+        self.tiles.first().map(|row| row.len()).unwrap_or(0)
     }
 }
 
@@ -144,3 +144,44 @@ impl<'a> SolutionInput<'a> for Map {
         Ok(Map { tiles, trailheads })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        day_test,
+        harness::{testing, Part, SolutionInput},
+    };
+
+    use super::{Map, D10};
+
+    const EXAMPLE: &str = "\
+89010123
+78121874
+87430965
+96549874
+45678903
+32019012
+01329801
+10456732
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D10, Part::One, EXAMPLE, 36);
+        day_test!(D10, Part::Two, EXAMPLE, 81);
+    }
+
+    #[test]
+    fn test_score_bounded_by_rating() {
+        testing::check(
+            "a trailhead's score never exceeds its rating",
+            |rng| testing::trail_grid(rng, 8, 8),
+            |input| {
+                let map = Map::read(input.as_bytes()).unwrap();
+                map.trailheads
+                    .iter()
+                    .all(|th| map.compute_score(th) <= map.compute_rating(th))
+            },
+        );
+    }
+}
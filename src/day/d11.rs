@@ -1,7 +1,7 @@
 use std::collections::{HashMap, VecDeque};
 
 use crate::{
-    harness::{Day, Solution, SolutionInput},
+    harness::{Day, Param, Params, Solution, SolutionInput},
     parse,
 };
 
@@ -17,6 +17,10 @@ impl Day for D11 {
     }
 }
 
+static P1_BLINKS: Param<u64> = Param::new("blinks", 25);
+
+static P2_BLINKS: Param<usize> = Param::new("blinks", 75);
+
 pub struct P1;
 
 impl<'a> Solution<'a> for P1 {
@@ -25,11 +29,16 @@ impl<'a> Solution<'a> for P1 {
     type Output = u64;
 
     fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
+        Self::solve_with(input, &Params::new())
+    }
+
+    fn solve_with(input: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+        let total_blinks = params.get(&P1_BLINKS)?;
         let mut count = 0;
 
         let mut queue: VecDeque<(u64, u64)> = input.iter().map(|n| (*n, 0)).collect();
         while let Some((stone, blinks)) = queue.pop_front() {
-            if blinks == 25 {
+            if blinks == total_blinks {
                 count += 1;
                 continue;
             }
@@ -56,10 +65,15 @@ impl<'a> Solution<'a> for P2 {
     type Output = usize;
 
     fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
+        Self::solve_with(input, &Params::new())
+    }
+
+    fn solve_with(input: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+        let blinks = params.get(&P2_BLINKS)?;
         let mut cache: HashMap<(u64, usize), usize> = HashMap::new();
         Ok(input
             .iter()
-            .map(|stone| dfs_count(*stone, 75, &mut cache))
+            .map(|stone| dfs_count(*stone, blinks, &mut cache))
             .sum())
     }
 }
@@ -92,7 +106,7 @@ fn split_digits(n: u64) -> Option<(u64, u64)> {
     }
 
     let digits = n.ilog10() + 1;
-    if digits % 2 != 0 {
+    if !digits.is_multiple_of(2) {
         return None;
     }
 
@@ -114,3 +128,22 @@ impl<'a> SolutionInput<'a> for Vec<u64> {
         Ok(v)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{day_test, harness::Part};
+
+    use super::D11;
+
+    const EXAMPLE: &str = "\
+125 17
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D11, Part::One, EXAMPLE, 55312);
+        day_test!(D11, Part::Two, EXAMPLE, 65601038650482_u64);
+        day_test!(D11, Part::One, EXAMPLE, 22, blinks = 6);
+        day_test!(D11, Part::Two, EXAMPLE, 22, blinks = 6);
+    }
+}
@@ -186,7 +186,7 @@ impl Map {
     }
 
     pub fn cols(&self) -> usize {
-        if let Some(row) = self.plots.get(0) {
+        if let Some(row) = self.plots.first() {
             row.len()
         } else {
             0
@@ -205,3 +205,49 @@ impl<'a> SolutionInput<'a> for Map {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        day_test,
+        harness::{testing, Day, Params, Part},
+    };
+
+    use super::D12;
+
+    const EXAMPLE: &str = "\
+RRRRIICCFF
+RRRRIICCCF
+VVRRRCCFFF
+VVRCCCJFFF
+VVVVCJJCFE
+VVIVCCJJEE
+VVIIICJJEE
+MIIIIIJJEE
+MIIISIJEEE
+MMMISSJEEE
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D12, Part::One, EXAMPLE, 1930);
+        day_test!(D12, Part::Two, EXAMPLE, 1206);
+    }
+
+    #[test]
+    fn test_bulk_price_bounded_by_fence_price() {
+        testing::check(
+            "the bulk discount never raises the price",
+            |rng| testing::char_grid(rng, 6, 6, &['A', 'B', 'C']),
+            |input| {
+                let price = |part| -> usize {
+                    D12::run_with(part, input.as_bytes(), &Params::new())
+                        .unwrap()
+                        .parse()
+                        .unwrap()
+                };
+                price(Part::Two) <= price(Part::One)
+            },
+        );
+    }
+}
@@ -1,10 +1,9 @@
-use std::collections::HashMap;
-
 use once_cell::sync::Lazy;
 use regex::Regex;
+use thiserror::Error;
 
 use crate::{
-    harness::{iter, Day, InputError, Inputs, Solution, SolutionInput},
+    harness::{iter, Day, Error, InputError, Inputs, Param, Params, Solution, SolutionInput},
     parse,
 };
 
@@ -20,7 +19,9 @@ impl Day for D13 {
     }
 }
 
-static BTN_PRESS_LIMIT: u64 = 100;
+static BTN_PRESS_LIMIT: Param<u64> = Param::new("press_limit", 100);
+
+static PRIZE_OFFSET: Param<u64> = Param::new("prize_offset", 10_000_000_000_000);
 
 pub struct P1;
 
@@ -30,23 +31,20 @@ impl<'a> Solution<'a> for P1 {
     type Output = u64;
 
     fn solve(machines: Self::Input) -> crate::harness::Result<Self::Output> {
-        machines.fold_solve(0, |tokens, machine| Ok(tokens + min_tokens(&machine)))
+        Self::solve_with(machines, &Params::new())
     }
-}
 
-fn min_tokens(machine: &Machine) -> u64 {
-    for a_presses in 0..(BTN_PRESS_LIMIT + 1) {
-        for b_presses in 0..(BTN_PRESS_LIMIT + 1) {
-            let moved_by_a = (a_presses * machine.move_a.0, a_presses * machine.move_a.1);
-            let moved_by_b = (b_presses * machine.move_b.0, b_presses * machine.move_b.1);
-            let end_position = (moved_by_a.0 + moved_by_b.0, moved_by_a.1 + moved_by_b.1);
-            if end_position == machine.prize_position {
-                return a_presses * 3 + b_presses;
-            }
-        }
+    fn solve_with(machines: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+        let press_limit = params.get(&BTN_PRESS_LIMIT)?;
+        machines.fold_solve(0_u64, |total, machine| {
+            let (x, y) = machine.prize_position;
+            total
+                .checked_add(min_tokens(&machine, Some(press_limit)).unwrap_or(0))
+                .ok_or_else(|| {
+                    Error::SolutionError(Box::new(PressLimitError { x, y, press_limit }))
+                })
+        })
     }
-
-    0
 }
 
 pub struct P2;
@@ -57,27 +55,173 @@ impl<'a> Solution<'a> for P2 {
     type Output = u64;
 
     fn solve(machines: Self::Input) -> crate::harness::Result<Self::Output> {
-        machines.fold_solve(0, |tokens, machine| Ok(tokens + fast_min_tokens(&machine)))
+        Self::solve_with(machines, &Params::new())
+    }
+
+    fn solve_with(machines: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+        let prize_offset = params.get(&PRIZE_OFFSET)?;
+        machines.fold_solve(0_u64, |total, mut machine| {
+            let (x, y) = machine.prize_position;
+            let overflow = || {
+                Error::SolutionError(Box::new(PrizeOffsetError {
+                    x,
+                    y,
+                    offset: prize_offset,
+                }))
+            };
+            let offset = |pos: u64| pos.checked_add(prize_offset).ok_or_else(overflow);
+            machine.prize_position = (offset(x)?, offset(y)?);
+            total
+                .checked_add(min_tokens(&machine, None).unwrap_or(0))
+                .ok_or_else(overflow)
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("tokens for the prize at X={x}, Y={y} overflow with a press limit of {press_limit}")]
+pub struct PressLimitError {
+    x: u64,
+    y: u64,
+    press_limit: u64,
+}
+
+#[derive(Debug, Error)]
+#[error("prize at X={x}, Y={y} overflows when offset by {offset}")]
+pub struct PrizeOffsetError {
+    x: u64,
+    y: u64,
+    offset: u64,
+}
+
+/// Fewest tokens needed to win the machine's prize, or `None` if it can't be
+/// won (or the cost doesn't fit in a `u64`), pressing each button at most
+/// `press_limit` times (if any).
+///
+/// Pressing A `a` times and B `b` times wins when
+///
+/// ```text
+/// a * ax + b * bx = px
+/// a * ay + b * by = py
+/// ```
+///
+/// When the buttons move in different directions this 2x2 system has exactly
+/// one solution, found with Cramer's rule. Otherwise the buttons are collinear
+/// and there may be many ways to reach the prize, so pick the cheapest.
+fn min_tokens(machine: &Machine, press_limit: Option<u64>) -> Option<u64> {
+    let (ax, ay) = (machine.move_a.0 as i128, machine.move_a.1 as i128);
+    let (bx, by) = (machine.move_b.0 as i128, machine.move_b.1 as i128);
+    let (px, py) = (
+        machine.prize_position.0 as i128,
+        machine.prize_position.1 as i128,
+    );
+    let limit = press_limit.map(i128::from);
+
+    let det = ax * by - ay * bx;
+    let (a, b) = if det != 0 {
+        let a_num = px * by - py * bx;
+        let b_num = ax * py - ay * px;
+        if a_num % det != 0 || b_num % det != 0 {
+            return None;
+        }
+        (a_num / det, b_num / det)
+    } else {
+        collinear_presses((ax, ay), (bx, by), (px, py), limit)?
+    };
+
+    let within_limit = |presses: i128| presses >= 0 && limit.is_none_or(|l| presses <= l);
+    if !within_limit(a) || !within_limit(b) || a * ax + b * bx != px || a * ay + b * by != py {
+        return None;
+    }
+
+    u64::try_from(3 * a + b).ok()
+}
+
+/// Cheapest `(a, b)` presses when the buttons move along the same line
+fn collinear_presses(
+    (ax, ay): (i128, i128),
+    (bx, by): (i128, i128),
+    (px, py): (i128, i128),
+    limit: Option<i128>,
+) -> Option<(i128, i128)> {
+    // the prize must lie on the buttons' line
+    let (dx, dy) = if (ax, ay) != (0, 0) {
+        (ax, ay)
+    } else {
+        (bx, by)
+    };
+    if dx * py - dy * px != 0 {
+        return None;
+    }
+
+    // and since everything is on one line, one axis determines the other
+    let (a_step, b_step, target) = if ax != 0 || bx != 0 {
+        (ax, bx, px)
+    } else {
+        (ay, by, py)
+    };
+
+    match (a_step, b_step) {
+        // neither button moves the claw
+        (0, 0) => (target == 0).then_some((0, 0)),
+        // pressing a button that doesn't move the claw is a waste of tokens
+        (0, _) => (target % b_step == 0).then_some((0, target / b_step)),
+        (_, 0) => (target % a_step == 0).then_some((target / a_step, 0)),
+        _ => cheapest_combination(a_step, b_step, target, limit),
+    }
+}
+
+/// Minimise `3a + b` where `a * a_step + b * b_step = target`, `a, b >= 0` and
+/// both are within `limit`, for positive steps.
+///
+/// With `g = gcd(a_step, b_step)` and one solution `(a0, b0)` from the extended
+/// Euclidean algorithm, every solution is `(a0 + k * b_step / g, b0 - k *
+/// a_step / g)`. The cost is linear in `k`, so the cheapest is at one end of
+/// the range of `k` that keeps both press counts in bounds.
+fn cheapest_combination(
+    a_step: i128,
+    b_step: i128,
+    target: i128,
+    limit: Option<i128>,
+) -> Option<(i128, i128)> {
+    let (g, x, y) = extended_gcd(a_step, b_step);
+    if target % g != 0 {
+        return None;
+    }
+    let (a0, b0) = (x * (target / g), y * (target / g));
+    let (a_inc, b_dec) = (b_step / g, a_step / g);
+
+    // a >= 0 and b >= 0
+    let mut k_min = div_ceil(-a0, a_inc);
+    let mut k_max = b0.div_euclid(b_dec);
+    if let Some(limit) = limit {
+        // a <= limit and b <= limit
+        k_min = k_min.max(div_ceil(b0 - limit, b_dec));
+        k_max = k_max.min((limit - a0).div_euclid(a_inc));
+    }
+    if k_min > k_max {
+        return None;
+    }
+
+    let k = if 3 * a_inc > b_dec { k_min } else { k_max };
+    Some((a0 + k * a_inc, b0 - k * b_dec))
+}
+
+/// `(g, x, y)` such that `g = gcd(a, b) = a * x + b * y`, for positive `a`, `b`
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
     }
 }
 
-fn fast_min_tokens(_: &Machine) -> u64 {
-    // I tried a few things and couldn't get them working:
-    // 1) DFS w/ memoization - not fast enough
-    // 2) DP - couldn't figure out how to actually construct it. It reminded me
-    //    of the "make change" problem, but the subproblem space is so much
-    //    larger that I think you end up needing to do top-down DP (aka
-    //    memoization) which seemed to just end up being the DFS w/ memoization
-    //    approach.
-    // 3) Solving for variables via systems of equation approach. This is what
-    //    was suggested online. I did some pen and paper work and got A in terms
-    //    of B / B in terms of A, started coding it up, and just got a bit
-    //    frustrated with all the checking in rust for whether something divides
-    //    evenly or not.
-    todo!()
+fn div_ceil(n: i128, d: i128) -> i128 {
+    -((-n).div_euclid(d))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Machine {
     move_a: (u64, u64),
     move_b: (u64, u64),
@@ -100,9 +244,9 @@ impl TryFrom<&str> for Machine {
             source: None,
         };
 
-        let btn_a_caps = BTN_A_RE.captures(&s).ok_or_else(mk_err)?;
-        let btn_b_caps = BTN_B_RE.captures(&s).ok_or_else(mk_err)?;
-        let prize_caps = PRIZE_RE.captures(&s).ok_or_else(mk_err)?;
+        let btn_a_caps = BTN_A_RE.captures(s).ok_or_else(mk_err)?;
+        let btn_b_caps = BTN_B_RE.captures(s).ok_or_else(mk_err)?;
+        let prize_caps = PRIZE_RE.captures(s).ok_or_else(mk_err)?;
 
         Ok(Machine {
             move_a: (
@@ -123,20 +267,207 @@ impl TryFrom<&str> for Machine {
 
 impl<'a> SolutionInput<'a> for Box<dyn Inputs<Machine> + 'a> {
     fn read(reader: impl std::io::BufRead + 'a) -> crate::harness::input::Result<Self> {
-        let mut lines = iter::lines(reader)
-            .filter(|l_res| l_res.is_err() || l_res.as_ref().is_ok_and(|l| !l.trim().is_empty()));
-
-        Ok(Box::new(std::iter::from_fn(move || {
-            let strs: Vec<Result<_, _>> = lines.by_ref().take(3).collect();
-            if strs.is_empty() {
-                None
-            } else {
-                Some(
-                    strs.into_iter()
-                        .collect::<Result<String, _>>()
-                        .and_then(|machine_def| Machine::try_from(machine_def.as_str())),
-                )
+        Ok(Box::new(iter::sections(reader).map(|section| {
+            let section = section?;
+            if section.lines.len() != 3 {
+                return Err(InputError::InvalidInput {
+                    msg: format!(
+                        "Machine definition on line {} has {} lines, expected 3",
+                        section.line_num,
+                        section.lines.len()
+                    ),
+                    source: None,
+                });
             }
+            Machine::try_from(section.lines.join("\n").as_str())
+                .map_err(|err| err.at_line(section.line_num))
         })))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        day_test,
+        harness::{testing, Day, Params, Part},
+    };
+
+    use super::{min_tokens, Machine, D13};
+
+    const EXAMPLE: &str = "\
+Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Prize: X=12748, Y=12176
+
+Button A: X+17, Y+86
+Button B: X+84, Y+37
+Prize: X=7870, Y=6450
+
+Button A: X+69, Y+23
+Button B: X+27, Y+71
+Prize: X=18641, Y=10279
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D13, Part::One, EXAMPLE, 480);
+        day_test!(D13, Part::Two, EXAMPLE, 875318608908_u64);
+        day_test!(D13, Part::One, EXAMPLE, 280, press_limit = 80);
+        day_test!(D13, Part::Two, EXAMPLE, 480, prize_offset = 0);
+    }
+
+    #[test]
+    fn test_prize_offset_overflow() {
+        let mut params = Params::new();
+        params.set("prize_offset", u64::MAX.to_string());
+        let err = D13::run_with(Part::Two, EXAMPLE.as_bytes(), &params).unwrap_err();
+        assert_eq!(
+            format!(
+                "prize at X=8400, Y=5400 overflows when offset by {}",
+                u64::MAX
+            ),
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_token_total_overflow() {
+        // each prize costs about 2 * u64::MAX / 3 tokens, so the second overflows
+        let third = u64::MAX / 9 * 3;
+        let input = |x: u64, y: u64| {
+            format!(
+                "Button A: X+3, Y+0\nButton B: X+0, Y+1\nPrize: X={x}, Y={y}\n\n\
+                 Button A: X+3, Y+0\nButton B: X+0, Y+1\nPrize: X={}, Y={y}\n",
+                x + 3
+            )
+        };
+
+        let mut params = Params::new();
+        params.set("press_limit", u64::MAX.to_string());
+        let err = D13::run_with(Part::One, input(third, third).as_bytes(), &params).unwrap_err();
+        assert_eq!(
+            format!(
+                "tokens for the prize at X={}, Y={third} overflow with a press limit of {}",
+                third + 3,
+                u64::MAX
+            ),
+            err.to_string()
+        );
+
+        let mut params = Params::new();
+        params.set("prize_offset", third.to_string());
+        let err = D13::run_with(Part::Two, input(0, 0).as_bytes(), &params).unwrap_err();
+        assert_eq!(
+            format!("prize at X=3, Y=0 overflows when offset by {third}"),
+            err.to_string()
+        );
+    }
+
+    fn machine(move_a: (u64, u64), move_b: (u64, u64), prize_position: (u64, u64)) -> Machine {
+        Machine {
+            move_a,
+            move_b,
+            prize_position,
+        }
+    }
+
+    #[test]
+    fn test_min_tokens() {
+        assert_eq!(
+            Some(280),
+            min_tokens(&machine((94, 34), (22, 67), (8400, 5400)), Some(100))
+        );
+        assert_eq!(
+            None,
+            min_tokens(&machine((26, 66), (67, 21), (12748, 12176)), Some(100))
+        );
+        assert_eq!(
+            Some(459236326669),
+            min_tokens(
+                &machine((26, 66), (67, 21), (10000000012748, 10000000012176)),
+                None
+            )
+        );
+
+        // 3 * u64::MAX tokens
+        assert_eq!(
+            None,
+            min_tokens(&machine((1, 0), (0, 1), (u64::MAX, 0)), None)
+        );
+    }
+
+    #[test]
+    fn test_min_tokens_collinear() {
+        // B is cheaper per unit distance
+        assert_eq!(Some(2), min_tokens(&machine((1, 1), (3, 3), (6, 6)), None));
+        // A is cheaper per unit distance
+        assert_eq!(
+            Some(6),
+            min_tokens(&machine((6, 6), (1, 1), (12, 12)), None)
+        );
+        assert_eq!(
+            Some(7),
+            min_tokens(&machine((6, 6), (1, 1), (13, 13)), None)
+        );
+        // press limit forces some of the more expensive button
+        assert_eq!(
+            Some(150),
+            min_tokens(&machine((1, 1), (1, 1), (150, 150)), None)
+        );
+        assert_eq!(
+            Some(250),
+            min_tokens(&machine((1, 1), (1, 1), (150, 150)), Some(100))
+        );
+        assert_eq!(
+            None,
+            min_tokens(&machine((1, 1), (1, 1), (201, 201)), Some(100))
+        );
+        // prize off the buttons' line
+        assert_eq!(None, min_tokens(&machine((1, 1), (2, 2), (3, 4)), None));
+        // no combination lands exactly on the prize
+        assert_eq!(None, min_tokens(&machine((2, 2), (4, 4), (3, 3)), None));
+        assert_eq!(None, min_tokens(&machine((4, 2), (6, 3), (2, 1)), None));
+        assert_eq!(Some(7), min_tokens(&machine((4, 2), (6, 3), (14, 7)), None));
+        // buttons that don't move along an axis
+        assert_eq!(Some(3), min_tokens(&machine((0, 0), (0, 1), (0, 3)), None));
+        assert_eq!(Some(6), min_tokens(&machine((2, 0), (0, 0), (4, 0)), None));
+        assert_eq!(Some(0), min_tokens(&machine((0, 0), (0, 0), (0, 0)), None));
+        assert_eq!(None, min_tokens(&machine((0, 0), (0, 0), (1, 0)), None));
+    }
+
+    #[test]
+    fn test_min_tokens_matches_brute_force() {
+        let brute_force = |m: &Machine| {
+            (0..=10_u64)
+                .flat_map(|a| (0..=10_u64).map(move |b| (a, b)))
+                .filter(|(a, b)| {
+                    (
+                        a * m.move_a.0 + b * m.move_b.0,
+                        a * m.move_a.1 + b * m.move_b.1,
+                    ) == m.prize_position
+                })
+                .map(|(a, b)| 3 * a + b)
+                .min()
+        };
+
+        testing::check(
+            "solver agrees with trying every press count",
+            |rng| {
+                // small moves so that collinear buttons come up often
+                let mut coord = || rng.range(0, 4);
+                let (move_a, move_b) = ((coord(), coord()), (coord(), coord()));
+                let (a, b) = (rng.range(0, 10), rng.range(0, 10));
+                let mut prize_position = (a * move_a.0 + b * move_b.0, a * move_a.1 + b * move_b.1);
+                if rng.chance(1, 4) {
+                    prize_position.0 += 1;
+                }
+                machine(move_a, move_b, prize_position)
+            },
+            |m| min_tokens(m, Some(10)) == brute_force(m),
+        );
+    }
+}
@@ -1,10 +1,13 @@
-use std::fmt::{self, Write};
+use std::{
+    fmt::{self, Write},
+    num::NonZeroUsize,
+};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
 
 use crate::{
-    harness::{iter, Day, InputError, Solution, SolutionInput},
+    harness::{iter, Day, InputError, Param, Params, Solution, SolutionInput},
     parse,
 };
 
@@ -13,13 +16,19 @@ pub struct D14;
 impl Day for D14 {
     type P1<'a> = P1;
 
-    type P2<'a> = P1;
+    type P2<'a> = P2;
 
     fn day() -> u8 {
         14
     }
 }
 
+static WIDTH: Param<NonZeroUsize> = Param::new("width", NonZeroUsize::new(101).unwrap());
+
+static HEIGHT: Param<NonZeroUsize> = Param::new("height", NonZeroUsize::new(103).unwrap());
+
+static SECONDS: Param<usize> = Param::new("seconds", 100);
+
 pub struct P1;
 
 impl<'a> Solution<'a> for P1 {
@@ -27,12 +36,34 @@ impl<'a> Solution<'a> for P1 {
 
     type Output = u64;
 
-    fn solve(mut input: Self::Input) -> crate::harness::Result<Self::Output> {
-        input.tick_seconds(100);
+    fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
+        Self::solve_with(input, &Params::new())
+    }
+
+    fn solve_with(mut input: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+        input.resize(params)?;
+        input.tick_seconds(params.get(&SECONDS)?);
         Ok(input.safety_factor())
     }
 }
 
+pub struct P2;
+
+impl<'a> Solution<'a> for P2 {
+    type Input = Bathroom;
+
+    type Output = usize;
+
+    fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
+        Self::solve_with(input, &Params::new())
+    }
+
+    fn solve_with(mut input: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+        input.resize(params)?;
+        Ok(input.find_tree())
+    }
+}
+
 pub struct Bathroom {
     width: usize,
     height: usize,
@@ -40,6 +71,13 @@ pub struct Bathroom {
 }
 
 impl Bathroom {
+    /// Apply the room size parameters
+    pub fn resize(&mut self, params: &Params) -> crate::harness::Result<()> {
+        self.width = params.get(&WIDTH)?.get();
+        self.height = params.get(&HEIGHT)?.get();
+        Ok(())
+    }
+
     pub fn tick_seconds(&mut self, seconds: usize) {
         for robot in &mut self.robots {
             let velocity = (
@@ -53,10 +91,57 @@ impl Bathroom {
         }
     }
 
+    /// Seconds after which every robot is back where it started
+    pub fn period(&self) -> usize {
+        let gcd = |mut a: usize, mut b: usize| {
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            a
+        };
+        self.width / gcd(self.width, self.height) * self.height
+    }
+
+    /// Find the second at which the robots arrange themselves into a picture,
+    /// leaving them in that arrangement.
+    ///
+    /// Robots in the picture are packed together rather than scattered across
+    /// the room, so over one full period the frame with the smallest spread of
+    /// positions (total variance in x and y) is the picture.
+    pub fn find_tree(&mut self) -> usize {
+        let mut best = (self.spread(), 0);
+
+        for second in 1..self.period() {
+            self.tick_seconds(1);
+            best = best.min((self.spread(), second));
+        }
+
+        // one more tick completes the period, back to the starting positions
+        self.tick_seconds(1 + best.1);
+        best.1
+    }
+
+    /// Variance of the robots' positions (summed over both axes), scaled by
+    /// the squared robot count to stay in integers
+    fn spread(&self) -> u128 {
+        let n = self.robots.len() as u128;
+        let (mut sum_x, mut sum_y, mut sum_x2, mut sum_y2) = (0, 0, 0, 0);
+
+        for robot in &self.robots {
+            let (x, y) = (robot.pos.0 as u128, robot.pos.1 as u128);
+            sum_x += x;
+            sum_y += y;
+            sum_x2 += x * x;
+            sum_y2 += y * y;
+        }
+
+        (n * sum_x2 - sum_x * sum_x) + (n * sum_y2 - sum_y * sum_y)
+    }
+
     pub fn safety_factor(&self) -> u64 {
         let (mid_x, mid_y) = (self.width / 2, self.height / 2);
-        let even_width = self.width % 2 == 0;
-        let even_height = self.height % 2 == 0;
+        let even_width = self.width.is_multiple_of(2);
+        let even_height = self.height.is_multiple_of(2);
         let mut top_left = 0;
         let mut top_right = 0;
         let mut bot_left = 0;
@@ -118,8 +203,8 @@ pub struct Robot {
 impl<'a> SolutionInput<'a> for Bathroom {
     fn read(reader: impl std::io::BufRead + 'a) -> crate::harness::input::Result<Self> {
         Ok(Bathroom {
-            width: 101,
-            height: 103,
+            width: WIDTH.default.get(),
+            height: HEIGHT.default.get(),
             robots: iter::lines(reader)
                 .map(|line_res| line_res.and_then(Robot::try_from))
                 .collect::<Result<Vec<_>, _>>()?,
@@ -152,3 +237,341 @@ impl TryFrom<String> for Robot {
         })
     }
 }
+
+/// Animation of the robots' movement, exported as numbered PGM frames or
+/// played back in the terminal
+pub mod anim {
+    use std::{
+        fs::{self, File},
+        io::{self, BufRead, BufWriter, Write},
+        ops::Range,
+        path::{Path, PathBuf},
+        sync::mpsc::{self, Receiver, RecvTimeoutError},
+        thread,
+        time::Duration,
+    };
+
+    use crate::render::{ansi, Image, BLACK, WHITE};
+
+    use super::Bathroom;
+
+    /// Render the robots' current positions, one `scale`x`scale` block of
+    /// pixels per tile
+    pub fn render(bathroom: &Bathroom, scale: usize) -> Image {
+        let mut image = Image::new(bathroom.width * scale, bathroom.height * scale, BLACK);
+        for robot in &bathroom.robots {
+            image.fill_cell(robot.pos.0, robot.pos.1, scale, WHITE);
+        }
+        image
+    }
+
+    /// Write a PGM frame per second in `seconds` to `dir` (named by second,
+    /// e.g. `frame_00042.pgm`), starting from the bathroom's current state as
+    /// second 0. Returns the written paths.
+    pub fn export_frames(
+        bathroom: &mut Bathroom,
+        seconds: Range<usize>,
+        dir: &Path,
+        scale: usize,
+    ) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        bathroom.tick_seconds(seconds.start);
+
+        let mut paths = vec![];
+        for second in seconds {
+            let path = dir.join(format!("frame_{:05}.pgm", second));
+            render(bathroom, scale).write_pgm(BufWriter::new(File::create(&path)?))?;
+            paths.push(path);
+            bathroom.tick_seconds(1);
+        }
+
+        Ok(paths)
+    }
+
+    /// Playback control, one per line of input
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Control {
+        /// Pause or resume (empty line or `p`)
+        TogglePause,
+        /// Pause and step forward a second (`n`)
+        Step,
+        /// Pause and step back a second (`b`)
+        Back,
+        /// Stop playback (`q`)
+        Quit,
+    }
+
+    impl Control {
+        pub fn parse(line: &str) -> Option<Self> {
+            match line.trim() {
+                "" | "p" => Some(Self::TogglePause),
+                "n" => Some(Self::Step),
+                "b" => Some(Self::Back),
+                "q" => Some(Self::Quit),
+                _ => None,
+            }
+        }
+    }
+
+    /// Read playback controls from `input` (e.g. stdin) on a background thread
+    pub fn controls(input: impl BufRead + Send + 'static) -> Receiver<Control> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in input.lines() {
+                let Ok(line) = line else { break };
+                if let Some(control) = Control::parse(&line) {
+                    if tx.send(control).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        rx
+    }
+
+    /// Play the frames for `seconds` to `out` at `fps` frames per second,
+    /// starting from the bathroom's current state as second 0. Playback ends
+    /// after the last frame, on [`Control::Quit`], or when paused with no more
+    /// controls to come.
+    pub fn play(
+        bathroom: &mut Bathroom,
+        seconds: Range<usize>,
+        fps: u32,
+        mut out: impl Write,
+        controls: &Receiver<Control>,
+    ) -> io::Result<()> {
+        if seconds.is_empty() {
+            return Ok(());
+        }
+
+        let frame_time = Duration::from_secs(1) / fps.max(1);
+        let period = bathroom.period();
+        let mut second = seconds.start;
+        let mut paused = false;
+        bathroom.tick_seconds(second);
+
+        loop {
+            write!(
+                out,
+                "{}{}second {}{}{}  [enter] pause/resume  [n] step  [b] back  [q] quit\n{:?}",
+                ansi::CLEAR,
+                ansi::BOLD,
+                second,
+                if paused { " (paused)" } else { "" },
+                ansi::RESET,
+                bathroom
+            )?;
+            out.flush()?;
+
+            let control = if paused {
+                match controls.recv() {
+                    Ok(control) => Some(control),
+                    Err(_) => break,
+                }
+            } else {
+                match controls.recv_timeout(frame_time) {
+                    Ok(control) => Some(control),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    // no more controls, keep playing
+                    Err(RecvTimeoutError::Disconnected) => {
+                        thread::sleep(frame_time);
+                        None
+                    }
+                }
+            };
+
+            match control {
+                None | Some(Control::Step) => {
+                    paused |= control.is_some();
+                    if second + 1 >= seconds.end {
+                        if control.is_none() {
+                            break;
+                        }
+                        continue;
+                    }
+                    second += 1;
+                    bathroom.tick_seconds(1);
+                }
+                Some(Control::Back) => {
+                    paused = true;
+                    if second > seconds.start {
+                        second -= 1;
+                        bathroom.tick_seconds(period - 1);
+                    }
+                }
+                Some(Control::TogglePause) => paused = !paused,
+                Some(Control::Quit) => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        day_test,
+        harness::{testing::Rng, Day, Params, Part},
+    };
+
+    use super::{anim, Bathroom, Robot, D14};
+
+    const EXAMPLE: &str = "\
+p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D14, Part::One, EXAMPLE, 12, width = 11, height = 7);
+    }
+
+    #[test]
+    fn test_zero_size() {
+        for (name, part) in [("width", Part::One), ("height", Part::Two)] {
+            let mut params = Params::new();
+            params.set(name, "0");
+            let err = D14::run_with(part, EXAMPLE.as_bytes(), &params).unwrap_err();
+            assert_eq!(
+                format!("invalid value for parameter '{}': '0'", name),
+                err.to_string()
+            );
+        }
+    }
+
+    /// Robots in a 101x103 room, half of which form a 10x10 block at
+    /// `picture_at` seconds while the rest are scattered
+    fn picture_bathroom(picture_at: isize) -> Bathroom {
+        let (width, height) = (101, 103);
+        let mut rng = Rng::new(14);
+
+        let robots = (0..400)
+            .map(|i| {
+                let target = if i % 2 == 0 {
+                    (40 + rng.range(0, 9) as isize, 50 + rng.range(0, 9) as isize)
+                } else {
+                    (
+                        rng.range(0, width - 1) as isize,
+                        rng.range(0, height - 1) as isize,
+                    )
+                };
+                let velocity = (
+                    rng.range(0, 200) as isize - 100,
+                    rng.range(0, 200) as isize - 100,
+                );
+                let pos = (
+                    (target.0 - velocity.0 * picture_at).rem_euclid(width as isize) as usize,
+                    (target.1 - velocity.1 * picture_at).rem_euclid(height as isize) as usize,
+                );
+                Robot { pos, velocity }
+            })
+            .collect();
+
+        Bathroom {
+            width: width as usize,
+            height: height as usize,
+            robots,
+        }
+    }
+
+    #[test]
+    fn test_find_tree() {
+        let mut bathroom = picture_bathroom(4242);
+        assert_eq!(4242, bathroom.find_tree());
+        assert!(bathroom
+            .robots
+            .iter()
+            .step_by(2)
+            .all(|r| (40..50).contains(&r.pos.0) && (50..60).contains(&r.pos.1)));
+    }
+
+    #[test]
+    fn test_dump_tree() {
+        // `inspect d14 --tree` prints the room as `find_tree` leaves it
+        let mut bathroom = picture_bathroom(4242);
+        let second = bathroom.find_tree();
+        let dump = format!("{:?}", bathroom);
+
+        let mut expected = picture_bathroom(4242);
+        expected.tick_seconds(second);
+        assert_eq!(format!("{:?}", expected), dump);
+
+        assert_eq!(103, dump.lines().count());
+    }
+
+    fn small_bathroom() -> Bathroom {
+        Bathroom {
+            width: 3,
+            height: 2,
+            robots: vec![Robot {
+                pos: (0, 0),
+                velocity: (1, 1),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_export_frames() {
+        let dir = std::env::temp_dir().join(format!("aoc24-d14-frames-{}", std::process::id()));
+        let mut bathroom = small_bathroom();
+
+        let paths = anim::export_frames(&mut bathroom, 2..4, &dir, 1).unwrap();
+
+        assert_eq!(
+            vec![dir.join("frame_00002.pgm"), dir.join("frame_00003.pgm")],
+            paths
+        );
+        // at second 2 the robot is at (2, 0)
+        assert_eq!(
+            b"P5\n3 2\n255\n\0\0\xff\0\0\0".to_vec(),
+            std::fs::read(&paths[0]).unwrap()
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_play() {
+        use anim::Control;
+
+        let render = |controls: Vec<Control>| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            for control in controls {
+                tx.send(control).unwrap();
+            }
+            drop(tx);
+
+            let mut out = vec![];
+            anim::play(&mut small_bathroom(), 0..3, 1000, &mut out, &rx).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let frames = |out: &str| -> Vec<String> {
+            out.split("second ")
+                .skip(1)
+                .map(|frame| frame.split("\x1b").next().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(vec!["0", "1", "2"], frames(&render(vec![])));
+        assert_eq!(
+            vec!["0", "0 (paused)", "1 (paused)", "2 (paused)", "1 (paused)"],
+            frames(&render(vec![
+                Control::TogglePause,
+                Control::Step,
+                Control::Step,
+                Control::Back,
+            ]))
+        );
+        assert_eq!(vec!["0"], frames(&render(vec![Control::Quit])));
+    }
+}
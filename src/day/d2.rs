@@ -1,5 +1,7 @@
+use std::fmt;
+
 use crate::{
-    harness::{iter, Day, InputError, Inputs, SolutionInput},
+    harness::{iter, Day, InputError, Inputs, Param, SolutionInput},
     parse,
 };
 
@@ -26,21 +28,15 @@ mod p1 {
         type Output = usize;
 
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
-            input.fold_solve(0, |count, report| {
-                Ok(if report.safety().is_safe() {
-                    count + 1
-                } else {
-                    count
-                })
-            })
+            input.try_count_where(|report| report.safety().is_safe())
         }
     }
 }
 
 mod p2 {
-    use crate::harness::{Inputs, Solution};
+    use crate::harness::{Inputs, Params, Solution};
 
-    use super::Report;
+    use super::{Report, TOLERANCE};
 
     pub struct P2;
 
@@ -49,22 +45,27 @@ mod p2 {
         type Output = usize;
 
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
-            input.fold_solve(0, |count, report| {
-                Ok(if report.safe_with_tolerance() {
-                    count + 1
-                } else {
-                    count
-                })
-            })
+            Self::solve_with(input, &Params::new())
+        }
+
+        fn solve_with(input: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+            let tolerance = params.get(&TOLERANCE)?;
+            input.try_count_where(|report| report.safe_with_tolerance(tolerance))
         }
     }
 }
 
+/// Number of levels which may be removed to make a report safe
+static TOLERANCE: Param<usize> = Param::new("tolerance", 1);
+
 impl<'a> SolutionInput<'a> for Box<dyn Inputs<Report> + 'a> {
     fn read(reader: impl std::io::BufRead + 'a) -> Result<Self, crate::harness::InputError> {
-        Ok(Box::new(iter::lines(reader).map(|line| {
-            Report::parse(line?.split_whitespace().collect::<Vec<_>>())
-        })))
+        Ok(Box::new(iter::lines(reader).enumerate_lines().try_map(
+            |(line_num, line)| {
+                Report::parse(line.split_whitespace().collect::<Vec<_>>())
+                    .map_err(|err| err.at_line(line_num))
+            },
+        )))
     }
 }
 
@@ -82,6 +83,39 @@ impl SafetyReport {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum Diagnosis {
+    Safe,
+    /// Safe once these levels are removed, as `(index, level)`
+    Fixable(Vec<(usize, u8)>),
+    /// More than the tolerated number of levels have to be removed
+    Unsafe {
+        min_removals: usize,
+    },
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnosis::Safe => write!(f, "safe"),
+            Diagnosis::Fixable(removals) => {
+                write!(f, "fixable by removing")?;
+                for (n, (idx, level)) in removals.iter().enumerate() {
+                    let sep = if n == 0 { "" } else { "," };
+                    write!(f, "{} index {} ({})", sep, idx, level)?;
+                }
+                Ok(())
+            }
+            Diagnosis::Unsafe { min_removals } => write!(
+                f,
+                "unsafe, needs {} level{} removed",
+                min_removals,
+                if *min_removals == 1 { "" } else { "s" }
+            ),
+        }
+    }
+}
+
 impl Report {
     fn safety(&self) -> SafetyReport {
         let levels = &self.0;
@@ -108,33 +142,76 @@ impl Report {
         SafetyReport::Safe
     }
 
-    fn safe_with_tolerance(&self) -> bool {
-        let idx = if let SafetyReport::Unsafe(idx) = self.safety() {
-            idx
-        } else {
-            return true;
-        };
+    pub fn safe_with_tolerance(&self, tolerance: usize) -> bool {
+        self.removals().len() <= tolerance
+    }
 
-        let report_without = |idx: usize| {
-            Report(
-                self.0
-                    .iter()
-                    .enumerate()
-                    .filter(|(i, _)| *i != idx)
-                    .map(|(_, lvl)| *lvl)
-                    .collect::<Vec<_>>(),
-            )
-        };
+    pub fn levels(&self) -> &[u8] {
+        &self.0
+    }
 
-        let mut reports = vec![report_without(idx)];
-        if idx < self.0.len() - 1 {
-            reports.push(report_without(idx + 1));
-        }
-        if idx > 0 {
-            reports.push(report_without(idx - 1));
+    /// Indices of the fewest levels to remove to make the report safe.
+    ///
+    /// The levels kept have to be a longest safe subsequence, found for each
+    /// direction with the longest safe run ending at each level: the longest
+    /// ending at any earlier level it can follow, plus one. Ties keep later
+    /// levels, so e.g. the first of two equal levels is removed.
+    pub fn removals(&self) -> Vec<usize> {
+        let levels = &self.0;
+        if levels.len() < 2 {
+            return vec![];
         }
 
-        reports.iter().any(|r| r.safety().is_safe())
+        let longest_run = |follows: fn(u8, u8) -> bool| {
+            // (length, previous index) of the longest safe run ending at each
+            // level
+            let mut runs: Vec<(usize, Option<usize>)> = Vec::with_capacity(levels.len());
+            for (idx, level) in levels.iter().enumerate() {
+                let best = (0..idx)
+                    .filter(|prev| follows(levels[*prev], *level))
+                    .max_by_key(|prev| (runs[*prev].0, *prev));
+                runs.push(match best {
+                    Some(prev) => (runs[prev].0 + 1, Some(prev)),
+                    None => (1, None),
+                });
+            }
+
+            let mut end = (0..runs.len()).max_by_key(|idx| (runs[*idx].0, *idx));
+            let mut kept = vec![false; levels.len()];
+            while let Some(idx) = end {
+                kept[idx] = true;
+                end = runs[idx].1;
+            }
+            kept
+        };
+
+        [
+            longest_run(|prev, cur| (1..=3).contains(&cur.wrapping_sub(prev))),
+            longest_run(|prev, cur| (1..=3).contains(&prev.wrapping_sub(cur))),
+        ]
+        .into_iter()
+        .map(|kept| {
+            kept.iter()
+                .enumerate()
+                .filter(|(_, kept)| !**kept)
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>()
+        })
+        .min_by_key(Vec::len)
+        .unwrap()
+    }
+
+    /// Whether the report is safe, or which levels to remove to make it so
+    /// when that's at most `tolerance` levels
+    pub fn diagnose(&self, tolerance: usize) -> Diagnosis {
+        let removals = self.removals();
+        match removals.len() {
+            0 => Diagnosis::Safe,
+            n if n <= tolerance => {
+                Diagnosis::Fixable(removals.into_iter().map(|idx| (idx, self.0[idx])).collect())
+            }
+            n => Diagnosis::Unsafe { min_removals: n },
+        }
     }
 
     fn parse(input: Vec<&str>) -> Result<Self, InputError> {
@@ -151,7 +228,12 @@ impl Report {
 
 #[cfg(test)]
 mod test {
-    use super::{Report, SafetyReport};
+    use crate::{
+        day_test,
+        harness::{testing, Part},
+    };
+
+    use super::{Diagnosis, Report, SafetyReport, D2};
 
     #[test]
     fn test_is_safe() {
@@ -166,7 +248,100 @@ mod test {
 
         for (report, expected_safety, expected_safe_with_tolerance) in inputs {
             assert_eq!(expected_safety, report.safety());
-            assert_eq!(expected_safe_with_tolerance, report.safe_with_tolerance());
+            assert_eq!(expected_safe_with_tolerance, report.safe_with_tolerance(1));
         }
     }
+
+    const EXAMPLE: &str = "\
+7 6 4 2 1
+1 2 7 8 9
+9 7 6 2 1
+1 3 2 4 5
+8 6 4 4 1
+1 3 6 7 9
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D2, Part::One, EXAMPLE, 2);
+        day_test!(D2, Part::Two, EXAMPLE, 4);
+    }
+
+    #[test]
+    fn test_diagnose() {
+        assert_eq!(Diagnosis::Safe, Report(vec![7, 6, 4, 2, 1]).diagnose(1));
+        assert_eq!(
+            Diagnosis::Fixable(vec![(1, 3)]),
+            Report(vec![1, 3, 2, 4, 5]).diagnose(1)
+        );
+        assert_eq!(
+            Diagnosis::Fixable(vec![(2, 4)]),
+            Report(vec![8, 6, 4, 4, 1]).diagnose(1)
+        );
+        // the first level is the odd one out
+        assert_eq!(
+            Diagnosis::Fixable(vec![(0, 9)]),
+            Report(vec![9, 1, 2, 3]).diagnose(1)
+        );
+        assert_eq!(
+            Diagnosis::Unsafe { min_removals: 2 },
+            Report(vec![1, 2, 7, 8, 9]).diagnose(1)
+        );
+        assert_eq!(
+            Diagnosis::Fixable(vec![(2, 7), (3, 8)]),
+            Report(vec![1, 2, 7, 8, 3]).diagnose(2)
+        );
+        assert_eq!(
+            "fixable by removing index 2 (7), index 3 (8)",
+            Report(vec![1, 2, 7, 8, 3]).diagnose(2).to_string()
+        );
+        assert_eq!(
+            "unsafe, needs 1 level removed",
+            Report(vec![1, 3, 2, 4, 5]).diagnose(0).to_string()
+        );
+        assert_eq!(
+            "unsafe, needs 2 levels removed",
+            Report(vec![1, 2, 7, 8, 9]).diagnose(1).to_string()
+        );
+    }
+
+    #[test]
+    fn test_removals_are_minimal() {
+        testing::check(
+            "removals match the smallest subset found by brute force",
+            |rng| {
+                let len = rng.range(0, 8) as usize;
+                let start = rng.range(1, 50);
+                let mut levels = vec![];
+                let mut level = start as i64;
+                for _ in 0..len {
+                    level += rng.range(0, 8) as i64 - 4;
+                    levels.push(level.clamp(0, 99) as u8);
+                }
+                levels
+            },
+            |levels| {
+                let report = Report(levels.clone());
+                let removals = report.removals();
+                let without = |mask: u32| {
+                    Report(
+                        levels
+                            .iter()
+                            .enumerate()
+                            .filter(|(idx, _)| mask & (1 << idx) == 0)
+                            .map(|(_, level)| *level)
+                            .collect(),
+                    )
+                };
+                let fewest = (0..1_u32 << levels.len())
+                    .filter(|mask| without(*mask).safety().is_safe())
+                    .map(u32::count_ones)
+                    .min()
+                    .unwrap();
+
+                let mask = removals.iter().fold(0, |mask, idx| mask | 1 << idx);
+                removals.len() == fewest as usize && without(mask).safety().is_safe()
+            },
+        );
+    }
 }
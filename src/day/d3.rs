@@ -1,8 +1,11 @@
+use std::{collections::VecDeque, fmt, io::BufRead, ops::Range};
+
 use once_cell::sync::Lazy;
-use regex::Regex;
+use regex::bytes::{Regex, RegexBuilder};
+use thiserror::Error;
 
 use crate::{
-    harness::{input, iter, Day, Inputs, Part, SolutionInput},
+    harness::{input, Day, InputError, Inputs, Part, SolutionInput},
     parse::parse_u32,
 };
 
@@ -20,7 +23,7 @@ impl Day for D3 {
 pub mod p1 {
     use crate::{
         day::d3::Machine,
-        harness::{Inputs, Part, Solution},
+        harness::{Error, Inputs, Part, Solution},
     };
 
     use super::Instruction;
@@ -34,9 +37,10 @@ pub mod p1 {
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
             input
                 .fold_solve(Machine::new(Part::One), |exec, instruction| {
-                    Ok(exec.execute(instruction))
+                    exec.execute(instruction)
+                        .map_err(|err| Error::SolutionError(Box::new(err)))
                 })
-                .map(|machine| machine.count)
+                .map(|machine| machine.count())
         }
     }
 }
@@ -44,7 +48,7 @@ pub mod p1 {
 pub mod p2 {
     use crate::{
         day::d3::Machine,
-        harness::{Inputs, Part, Solution},
+        harness::{Error, Inputs, Part, Solution},
     };
 
     use super::Instruction;
@@ -58,117 +62,451 @@ pub mod p2 {
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
             input
                 .fold_solve(Machine::new(Part::Two), |machine, instruction| {
-                    Ok(machine.execute(instruction))
+                    machine
+                        .execute(instruction)
+                        .map_err(|err| Error::SolutionError(Box::new(err)))
                 })
-                .map(|machine| machine.count)
+                .map(|machine| machine.count())
         }
     }
 }
 
 impl<'a> SolutionInput<'a> for Box<dyn Inputs<Instruction> + 'a> {
     fn read(reader: impl std::io::BufRead + 'a) -> input::Result<Self> {
-        Ok(Box::new(iter::lines(reader).flat_map(|line| {
-            if line.is_ok() {
-                find_instructions(&line.unwrap())
-                    .into_iter()
-                    .map(Result::Ok)
-                    .collect()
-            } else {
-                vec![Err(line.err().unwrap())]
-            }
-        })))
+        Ok(Box::new(Scanner::new(reader, &STANDARD)))
     }
 }
-#[derive(Debug, PartialEq)]
-pub enum Instruction {
-    Mul(u32, u32),
-    Do,
-    Dont,
-}
 
-#[derive(Clone, Copy)]
-pub struct Machine {
-    part: Part,
-    resumed: bool,
-    count: u32,
+/// Lazily finds instructions in a stream of memory, a chunk at a time, with
+/// the same results as searching all of it at once.
+///
+/// The end of each chunk is only searched once there's enough of the stream
+/// buffered for the longest instruction ([`InstructionSet::max_len`]) to
+/// match there, so up to that many bytes are carried over to the next chunk.
+/// With an unbounded instruction nothing is searched until the end of the
+/// stream.
+pub struct Scanner<'s, R> {
+    reader: R,
+    set: &'s InstructionSet,
+    /// Memory not yet searched, along with any partial instruction
+    buf: Vec<u8>,
+    /// Offset of the start of `buf` in memory
+    offset: usize,
+    found: VecDeque<Instruction>,
+    done: bool,
 }
 
-impl Machine {
-    pub fn new(part: Part) -> Self {
+impl<'s, R: BufRead> Scanner<'s, R> {
+    pub fn new(reader: R, set: &'s InstructionSet) -> Self {
         Self {
-            part,
-            resumed: true,
-            count: 0,
+            reader,
+            set,
+            buf: vec![],
+            offset: 0,
+            found: VecDeque::new(),
+            done: false,
         }
     }
 
-    pub fn execute(self, instruction: Instruction) -> Self {
-        match instruction {
-            Instruction::Mul(x, y) => {
-                if self.allow_mul() {
-                    let mut new = self.clone();
-                    new.count += x * y;
-                    new
-                } else {
-                    self
-                }
+    /// Search the buffer, dropping what can't be part of a later match
+    fn scan(&mut self, eof: bool) -> input::Result<()> {
+        // a match can only start where the longest possible instruction would
+        // fit before the end of the buffer, or anywhere once it's all there
+        let decided = match self.set.max_len() {
+            _ if eof => self.buf.len(),
+            Some(max_len) => (self.buf.len() + 1).saturating_sub(max_len),
+            None => 0,
+        };
+
+        let mut cut = decided;
+        for (range, def, instruction) in self.set.matches(&self.buf) {
+            if range.start >= decided && !eof {
+                break;
+            }
+            let mut instruction = instruction
+                .ok_or_else(|| InstructionSet::arg_error(def, self.offset + range.start))?;
+            instruction.offset += self.offset;
+            self.found.push_back(instruction);
+            cut = cut.max(range.end);
+        }
+
+        self.buf.drain(..cut);
+        self.offset += cut;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for Scanner<'_, R> {
+    type Item = input::Result<Instruction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(instruction) = self.found.pop_front() {
+                return Some(Ok(instruction));
             }
-            Instruction::Do => {
-                let mut new = self.clone();
-                new.resumed = true;
-                new
+            if self.done {
+                return None;
             }
-            Instruction::Dont => {
-                let mut new = self.clone();
-                new.resumed = false;
-                new
+
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+            };
+            let len = chunk.len();
+            self.buf.extend_from_slice(chunk);
+            self.reader.consume(len);
+
+            self.done = len == 0;
+            if let Err(err) = self.scan(self.done) {
+                self.done = true;
+                self.found.clear();
+                return Some(Err(err));
             }
         }
     }
+}
+
+/// Instruction found in memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    /// Byte offset in memory
+    pub offset: usize,
+    /// Name of the [`InstructionDef`] it matched
+    pub op: &'static str,
+    pub args: Vec<u32>,
+}
 
-    pub fn execute_all(self, instructions: impl Iterator<Item = Instruction>) -> Self {
-        instructions.fold(self, Self::execute)
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(u32::to_string).collect();
+        write!(f, "{}({})", self.op, args.join(","))
     }
+}
+
+/// Machine state instructions act on
+#[derive(Debug, Clone, Copy)]
+pub struct State {
+    pub part: Part,
+    pub resumed: bool,
+    pub count: u32,
+}
 
+impl State {
     pub fn allow_mul(&self) -> bool {
         self.part == Part::One || self.resumed
     }
 }
 
-fn find_instructions(haystack: &str) -> Vec<Instruction> {
-    static MUL_RE: Lazy<Regex> = Lazy::new(|| {
-        Regex::new(concat!(
-            r"(?<mul>mul\((?<mul_x>\d{1,3}),(?<mul_y>\d{1,3})\))",
-            r"|(?<do>do\(\))",
-            r"|(?<dont>don\'t\(\))",
-        ))
+/// What an instruction does with its arguments, returning whether it was
+/// applied (rather than ignored), or `None` if the count overflows
+pub type Effect = fn(&mut State, &[u32]) -> Option<bool>;
+
+/// Kind of instruction: how it appears in memory and what it does
+pub struct InstructionDef {
+    pub name: &'static str,
+    /// Regex for the instruction, capturing each of its (decimal) arguments,
+    /// or a comma separated list of them for variable arity
+    pub pattern: &'static str,
+    /// Longest the instruction can be in bytes, i.e. the longest `pattern`
+    /// can match, or `None` if it's unbounded
+    pub max_len: Option<usize>,
+    pub effect: Effect,
+}
+
+/// Instructions a machine understands
+pub struct InstructionSet {
+    defs: Vec<(InstructionDef, Regex)>,
+    /// All the patterns as alternatives, in registration order
+    any: Regex,
+}
+
+impl InstructionSet {
+    /// Patterns match bytes rather than Unicode, e.g. `\d` is an ASCII digit.
+    /// Fails if a pattern is invalid, or can match more than its `max_len`.
+    pub fn new(defs: Vec<InstructionDef>) -> Result<Self, InstructionSetError> {
+        let any = defs
+            .iter()
+            .map(|def| format!("(?:{})", def.pattern))
+            .collect::<Vec<_>>()
+            .join("|");
+        let defs = defs
+            .into_iter()
+            .map(|def| {
+                let re = Self::regex(&format!("^(?:{})$", def.pattern))?;
+                Self::check_max_len(&def)?;
+                Ok((def, re))
+            })
+            .collect::<Result<_, InstructionSetError>>()?;
+
+        Ok(Self {
+            defs,
+            any: Self::regex(&any)?,
+        })
+    }
+
+    fn regex(pattern: &str) -> Result<Regex, regex::Error> {
+        RegexBuilder::new(pattern).unicode(false).build()
+    }
+
+    fn check_max_len(def: &InstructionDef) -> Result<(), InstructionSetError> {
+        let Some(max_len) = def.max_len else {
+            return Ok(());
+        };
+        let longest = regex_syntax::ParserBuilder::new()
+            .unicode(false)
+            .utf8(false)
+            .build()
+            .parse(def.pattern)
+            .expect("a pattern which compiled to parse")
+            .properties()
+            .maximum_len();
+
+        if max_len == 0 || longest.is_none_or(|longest| longest > max_len) {
+            return Err(InstructionSetError::MaxLen {
+                name: def.name,
+                max_len,
+                longest,
+            });
+        }
+        Ok(())
+    }
+
+    /// `mul(x,y)`, and `do()`/`don't()` which only apply to part two
+    pub fn standard() -> Self {
+        Self::new(vec![
+            InstructionDef {
+                name: "mul",
+                pattern: r"mul\((\d{1,3}),(\d{1,3})\)",
+                max_len: Some("mul(123,123)".len()),
+                effect: |state, args| {
+                    if state.allow_mul() {
+                        let product = args[0].checked_mul(args[1])?;
+                        state.count = state.count.checked_add(product)?;
+                    }
+                    Some(state.allow_mul())
+                },
+            },
+            InstructionDef {
+                name: "do",
+                pattern: r"do\(\)",
+                max_len: Some("do()".len()),
+                effect: |state, _| {
+                    state.resumed = true;
+                    Some(state.part == Part::Two)
+                },
+            },
+            InstructionDef {
+                name: "don't",
+                pattern: r"don't\(\)",
+                max_len: Some("don't()".len()),
+                effect: |state, _| {
+                    state.resumed = false;
+                    Some(state.part == Part::Two)
+                },
+            },
+        ])
         .unwrap()
-    });
-    MUL_RE
-        .captures_iter(haystack)
-        .map(|caps| {
-            if caps.name("mul").is_some() {
-                Instruction::Mul(
-                    parse_u32(&caps["mul_x"]).unwrap(),
-                    parse_u32(&caps["mul_y"]).unwrap(),
-                )
-            } else if caps.name("do").is_some() {
-                Instruction::Do
-            } else {
-                Instruction::Dont
-            }
+    }
+
+    /// Instructions in `haystack`, which starts at byte `offset` of memory.
+    /// Fails on a match with an argument too large for a `u32`.
+    pub fn find(
+        &self,
+        haystack: impl AsRef<[u8]>,
+        offset: usize,
+    ) -> input::Result<Vec<Instruction>> {
+        self.matches(haystack.as_ref())
+            .map(|(range, def, instruction)| {
+                let mut instruction =
+                    instruction.ok_or_else(|| Self::arg_error(def, offset + range.start))?;
+                instruction.offset += offset;
+                Ok(instruction)
+            })
+            .collect()
+    }
+
+    fn arg_error(def: &InstructionDef, offset: usize) -> InputError {
+        let msg = format!(
+            "Instruction {} at byte {} has an argument too large for a u32",
+            def.name, offset
+        );
+        InputError::InvalidInput { msg, source: None }
+    }
+
+    /// Longest instruction in bytes, or `None` if any are unbounded
+    pub fn max_len(&self) -> Option<usize> {
+        self.defs
+            .iter()
+            .try_fold(1, |longest, (def, _)| Some(longest.max(def.max_len?)))
+    }
+
+    /// Byte range and definition of each match in `haystack`, with the
+    /// instruction (offset from the start of `haystack`) if its arguments fit
+    /// in a `u32`
+    fn matches<'h>(
+        &'h self,
+        haystack: &'h [u8],
+    ) -> impl Iterator<Item = (Range<usize>, &'h InstructionDef, Option<Instruction>)> + 'h {
+        self.any.find_iter(haystack).filter_map(|m| {
+            let (def, args) = self.instruction(m.as_bytes())?;
+            let instruction = args.map(|args| Instruction {
+                offset: m.start(),
+                op: def.name,
+                args,
+            });
+            Some((m.range(), def, instruction))
         })
-        .collect()
+    }
+
+    fn instruction(&self, matched: &[u8]) -> Option<(&InstructionDef, Option<Vec<u32>>)> {
+        // the first definition matching here is the alternative the combined
+        // regex took
+        let (def, caps) = self
+            .defs
+            .iter()
+            .find_map(|(def, re)| Some((def, re.captures(matched)?)))?;
+        let args = caps
+            .iter()
+            .skip(1)
+            .flatten()
+            .flat_map(|list| list.as_bytes().split(|b| *b == b','))
+            .map(|arg| parse_u32(std::str::from_utf8(arg).ok()?).ok())
+            .collect();
+        Some((def, args))
+    }
+
+    fn def(&self, op: &str) -> Option<&InstructionDef> {
+        self.defs
+            .iter()
+            .map(|(def, _)| def)
+            .find(|def| def.name == op)
+    }
+}
+
+/// Why an [`InstructionSet`] couldn't be built
+#[derive(Debug, Error)]
+pub enum InstructionSetError {
+    #[error(transparent)]
+    Pattern(#[from] regex::Error),
+
+    /// The scanner would miss longer matches split between chunks
+    #[error(
+        "max_len {max_len} of instruction {name} must be at least 1 and cover the longest match, {}",
+        Self::longest_str(longest)
+    )]
+    MaxLen {
+        name: &'static str,
+        max_len: usize,
+        longest: Option<usize>,
+    },
+}
+
+impl InstructionSetError {
+    fn longest_str(longest: &Option<usize>) -> String {
+        match longest {
+            Some(len) => format!("{} bytes", len),
+            None => "which is unbounded".to_string(),
+        }
+    }
+}
+
+static STANDARD: Lazy<InstructionSet> = Lazy::new(InstructionSet::standard);
+
+#[derive(Clone, Copy)]
+pub struct Machine<'s> {
+    set: &'s InstructionSet,
+    state: State,
+}
+
+impl Machine<'static> {
+    pub fn new(part: Part) -> Self {
+        Self::with_set(&STANDARD, part)
+    }
+}
+
+impl<'s> Machine<'s> {
+    pub fn with_set(set: &'s InstructionSet, part: Part) -> Self {
+        Self {
+            set,
+            state: State {
+                part,
+                resumed: true,
+                count: 0,
+            },
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.state.count
+    }
+
+    /// Execute an instruction, returning whether it was applied. Instructions
+    /// this machine doesn't know are ignored.
+    pub fn step(&mut self, instruction: &Instruction) -> Result<bool, OverflowError> {
+        match self.set.def(instruction.op) {
+            Some(def) => {
+                (def.effect)(&mut self.state, &instruction.args).ok_or_else(|| OverflowError {
+                    instruction: instruction.clone(),
+                })
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn execute(mut self, instruction: Instruction) -> Result<Self, OverflowError> {
+        self.step(&instruction)?;
+        Ok(self)
+    }
+
+    pub fn execute_all(
+        self,
+        mut instructions: impl Iterator<Item = Instruction>,
+    ) -> Result<Self, OverflowError> {
+        instructions.try_fold(self, Self::execute)
+    }
+
+    /// Execute the instructions, listing each with whether it was applied
+    pub fn trace(
+        &mut self,
+        instructions: impl IntoIterator<Item = Instruction>,
+    ) -> Result<Vec<(Instruction, bool)>, OverflowError> {
+        instructions
+            .into_iter()
+            .map(|instruction| {
+                let applied = self.step(&instruction)?;
+                Ok((instruction, applied))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("count overflows executing {instruction} at byte {}", instruction.offset)]
+pub struct OverflowError {
+    instruction: Instruction,
 }
 
 #[cfg(test)]
 mod test {
+    use std::io::BufReader;
+
     use crate::{
-        day::d3::{find_instructions, Instruction},
-        harness::Part,
+        day_test,
+        harness::{testing, Day, Params, Part},
     };
 
-    use super::Machine;
+    use super::{Instruction, InstructionDef, InstructionSet, Machine, Scanner, D3, STANDARD};
+
+    fn ins(offset: usize, op: &'static str, args: &[u32]) -> Instruction {
+        Instruction {
+            offset,
+            op,
+            args: args.to_vec(),
+        }
+    }
 
     #[test]
     fn test_find_instructions_pt1() {
@@ -176,12 +514,12 @@ mod test {
 
         assert_eq!(
             vec![
-                Instruction::Mul(2, 4),
-                Instruction::Mul(5, 5),
-                Instruction::Mul(11, 8),
-                Instruction::Mul(8, 5),
+                ins(1, "mul", &[2, 4]),
+                ins(29, "mul", &[5, 5]),
+                ins(53, "mul", &[11, 8]),
+                ins(62, "mul", &[8, 5]),
             ],
-            find_instructions(haystack)
+            STANDARD.find(haystack, 0).unwrap()
         );
     }
 
@@ -191,52 +529,299 @@ mod test {
 
         assert_eq!(
             vec![
-                Instruction::Mul(2, 4),
-                Instruction::Dont,
-                Instruction::Mul(5, 5),
-                Instruction::Mul(11, 8),
-                Instruction::Do,
-                Instruction::Mul(8, 5),
+                ins(1, "mul", &[2, 4]),
+                ins(20, "don't", &[]),
+                ins(28, "mul", &[5, 5]),
+                ins(48, "mul", &[11, 8]),
+                ins(59, "do", &[]),
+                ins(64, "mul", &[8, 5]),
+            ],
+            STANDARD.find(haystack, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_scanner_across_chunks() {
+        // every instruction is split between reads, and newlines are part of
+        // memory, so one breaks up the mul(5,5)
+        let memory =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,\n5)+mul(32,64](mul(11,8)un\ndo()?mul(8,5))";
+        let reader = BufReader::with_capacity(3, memory.as_bytes());
+
+        let found: Vec<Instruction> = Scanner::new(reader, &STANDARD)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                ins(1, "mul", &[2, 4]),
+                ins(20, "don't", &[]),
+                ins(49, "mul", &[11, 8]),
+                ins(61, "do", &[]),
+                ins(66, "mul", &[8, 5]),
             ],
-            find_instructions(haystack)
+            found
+        );
+    }
+
+    #[test]
+    fn test_scanner_matches_whole_memory() {
+        const FRAGMENTS: [&str; 12] = [
+            "mul(", "123", "4", ",", ")", "do()", "don't()", "do", "n't", "(", "\n", "x",
+        ];
+
+        testing::check(
+            "streamed instructions match searching all of memory at once",
+            |rng: &mut testing::Rng| {
+                let len = rng.index(40);
+                let memory: String = (0..len).map(|_| *rng.pick(&FRAGMENTS)).collect();
+                (memory, 1 + rng.index(16))
+            },
+            |(memory, capacity)| {
+                let reader = BufReader::with_capacity(*capacity, memory.as_bytes());
+                let found: Vec<Instruction> = Scanner::new(reader, &STANDARD)
+                    .collect::<Result<_, _>>()
+                    .unwrap();
+                found == STANDARD.find(memory, 0).unwrap()
+            },
         );
     }
 
     #[test]
     fn test_machine_pt1() {
         let instructions = vec![
-            Instruction::Mul(2, 4),
-            Instruction::Dont,
-            Instruction::Mul(5, 5),
-            Instruction::Mul(11, 8),
-            Instruction::Do,
-            Instruction::Mul(8, 5),
+            ins(1, "mul", &[2, 4]),
+            ins(20, "don't", &[]),
+            ins(28, "mul", &[5, 5]),
+            ins(48, "mul", &[11, 8]),
+            ins(59, "do", &[]),
+            ins(64, "mul", &[8, 5]),
         ];
         let expected_result = 161;
 
         let machine = Machine::new(Part::One);
         assert_eq!(
             expected_result,
-            machine.execute_all(instructions.into_iter()).count
+            machine
+                .execute_all(instructions.into_iter())
+                .unwrap()
+                .count()
         )
     }
 
     #[test]
     fn test_machine_pt2() {
         let instructions = vec![
-            Instruction::Mul(2, 4),
-            Instruction::Dont,
-            Instruction::Mul(5, 5),
-            Instruction::Mul(11, 8),
-            Instruction::Do,
-            Instruction::Mul(8, 5),
+            ins(1, "mul", &[2, 4]),
+            ins(20, "don't", &[]),
+            ins(28, "mul", &[5, 5]),
+            ins(48, "mul", &[11, 8]),
+            ins(59, "do", &[]),
+            ins(64, "mul", &[8, 5]),
         ];
         let expected_result = 48;
 
         let machine = Machine::new(Part::Two);
         assert_eq!(
             expected_result,
-            machine.execute_all(instructions.into_iter()).count
+            machine
+                .execute_all(instructions.into_iter())
+                .unwrap()
+                .count()
         )
     }
+
+    #[test]
+    fn test_example() {
+        day_test!(
+            D3,
+            Part::One,
+            "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))",
+            161
+        );
+        day_test!(
+            D3,
+            Part::Two,
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))",
+            48
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let haystack = r"xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
+        let mut machine = Machine::new(Part::Two);
+        let trace: Vec<(usize, bool)> = machine
+            .trace(STANDARD.find(haystack, 0).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|(instruction, applied)| (instruction.offset, applied))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, true),
+                (20, true),
+                (28, false),
+                (48, false),
+                (59, true),
+                (64, true)
+            ],
+            trace
+        );
+
+        // do() and don't() are matched but have no effect in part one
+        let mut machine = Machine::new(Part::One);
+        let applied: Vec<bool> = machine
+            .trace(STANDARD.find(haystack, 0).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|(_, applied)| applied)
+            .collect();
+        assert_eq!(vec![true, false, true, true, false, true], applied);
+        assert_eq!(161, machine.count());
+    }
+
+    /// `add(x,...)` of any length, and `neg()`
+    fn custom_set() -> InstructionSet {
+        InstructionSet::new(vec![
+            InstructionDef {
+                name: "add",
+                pattern: r"add\((\d+(?:,\d+)*)\)",
+                max_len: None,
+                effect: |state, args| {
+                    state.count = args
+                        .iter()
+                        .try_fold(state.count, |sum, arg| sum.checked_add(*arg))?;
+                    Some(true)
+                },
+            },
+            InstructionDef {
+                name: "neg",
+                pattern: r"neg\(\)",
+                max_len: Some("neg()".len()),
+                effect: |state, _| {
+                    state.count = 0;
+                    Some(true)
+                },
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_custom_instructions() {
+        let set = custom_set();
+        let found = set
+            .find("add(1,2)mul(3,4)add(5)neg()add(7,8,9)", 100)
+            .unwrap();
+        assert_eq!(
+            vec![
+                ins(100, "add", &[1, 2]),
+                ins(116, "add", &[5]),
+                ins(122, "neg", &[]),
+                ins(127, "add", &[7, 8, 9]),
+            ],
+            found
+        );
+        assert_eq!(
+            24,
+            Machine::with_set(&set, Part::One)
+                .execute_all(found.into_iter())
+                .unwrap()
+                .count()
+        );
+        assert_eq!("add(7,8)", ins(0, "add", &[7, 8]).to_string());
+    }
+
+    #[test]
+    fn test_scanner_unbounded_instruction() {
+        // much longer than anything STANDARD carries over between chunks
+        let args: Vec<String> = (1..=40).map(|n| n.to_string()).collect();
+        let memory = format!("neg()add({})neg()add(1)", args.join(","));
+        assert_eq!(None, custom_set().max_len());
+
+        let set = custom_set();
+        let reader = BufReader::with_capacity(4, memory.as_bytes());
+        let found: Vec<Instruction> = Scanner::new(reader, &set)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(set.find(&memory, 0).unwrap(), found);
+        assert_eq!(4, found.len());
+        assert_eq!((1..=40).collect::<Vec<u32>>(), found[1].args);
+    }
+
+    #[test]
+    fn test_invalid_max_len() {
+        let error = |pattern: &'static str, max_len: usize| {
+            InstructionSet::new(vec![InstructionDef {
+                name: "add",
+                pattern,
+                max_len: Some(max_len),
+                effect: |_, _| Some(true),
+            }])
+            .err()
+            .map(|err| err.to_string())
+        };
+
+        assert_eq!(
+            Some(
+                "max_len 8 of instruction add must be at least 1 and cover the longest match, \
+                 which is unbounded"
+                    .to_string()
+            ),
+            error(r"add\((\d+(?:,\d+)*)\)", "add(1,2)".len())
+        );
+        assert_eq!(
+            Some(
+                "max_len 7 of instruction add must be at least 1 and cover the longest match, \
+                 8 bytes"
+                    .to_string()
+            ),
+            error(r"add\((\d{1,3})\)", 7)
+        );
+        assert_eq!(
+            Some(
+                "max_len 0 of instruction add must be at least 1 and cover the longest match, \
+                 0 bytes"
+                    .to_string()
+            ),
+            error("", 0)
+        );
+        assert_eq!(None, error(r"add\((\d{1,3})\)", 8));
+        assert_eq!(None, error(r"add\((\d{1,3})\)", 100));
+
+        // `\d` is an ASCII digit, so a Unicode one can't make mul longer
+        assert!(STANDARD.find("mul(\u{661},2)", 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_count_overflow() {
+        // 4304 * 999 * 999 doesn't fit in a u32
+        let memory = "mul(999,999)".repeat(4304);
+        let err = D3::run_with(Part::One, memory.as_bytes(), &Params::new()).unwrap_err();
+        assert_eq!(
+            "count overflows executing mul(999,999) at byte 51636",
+            err.to_string()
+        );
+
+        let set = custom_set();
+        let found = set.find("add(4294967295)add(1)", 0).unwrap();
+        let err = Machine::with_set(&set, Part::One).trace(found).unwrap_err();
+        assert_eq!(
+            "count overflows executing add(1) at byte 15",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_argument_too_large() {
+        let set = custom_set();
+        let memory = "add(1)add(4294967296)";
+        let expected =
+            "failed to parse input: Instruction add at byte 6 has an argument too large for a u32";
+
+        assert_eq!(expected, set.find(memory, 0).unwrap_err().to_string());
+        let found: Result<Vec<Instruction>, _> = Scanner::new(memory.as_bytes(), &set).collect();
+        assert_eq!(expected, found.unwrap_err().to_string());
+    }
 }
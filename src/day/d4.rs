@@ -1,8 +1,17 @@
-use std::ops::{Add, Mul};
+use std::{
+    collections::HashSet,
+    fmt::{self, Write},
+    ops::{Add, Mul},
+    str::FromStr,
+};
 
 use once_cell::sync::Lazy;
+use thiserror::Error;
 
-use crate::harness::{input, iter, Day, SolutionInput};
+use crate::{
+    harness::{input, iter, Day, SolutionInput},
+    render::ansi,
+};
 
 pub struct D4;
 
@@ -16,11 +25,9 @@ impl Day for D4 {
 }
 
 pub mod p1 {
-    use std::collections::HashSet;
-
     use crate::harness::Solution;
 
-    use super::{Crossword, Point};
+    use super::Crossword;
 
     pub struct P1;
 
@@ -29,30 +36,15 @@ pub mod p1 {
         type Output = usize;
 
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
-            let mut visited_xs: HashSet<Point> = HashSet::new();
-
-            let mut count = 0;
-            for point in input.points() {
-                let xs: Vec<_> = input
-                    .find_xs(point)
-                    .into_iter()
-                    .filter(|p| !visited_xs.contains(p))
-                    .collect();
-                for p in xs {
-                    count += input.words_from_point("XMAS", p);
-                    visited_xs.insert(p);
-                }
-            }
-
-            Ok(count)
+            Ok(input.search(&["XMAS"]).len())
         }
     }
 }
 
 pub mod p2 {
-    use crate::{day::d4::Point, harness::Solution};
+    use crate::harness::Solution;
 
-    use super::Crossword;
+    use super::{Crossword, X_MAS};
 
     pub struct P2;
 
@@ -61,70 +53,98 @@ pub mod p2 {
         type Output = usize;
 
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
-            Ok(input
-                .points()
-                .filter(|p| input.get(*p) == Some('A'))
-                .filter(|p| is_x_mas(&input, p))
-                .count())
-        }
-    }
-
-    fn is_x_mas(cw: &Crossword, point: &Point) -> bool {
-        if cw.get(*point) != Some('A') {
-            return false;
+            Ok(input.find_template(&X_MAS).len())
         }
-
-        let nw = *point + Point::new(-1, -1);
-        let ne = *point + Point::new(1, -1);
-        let se = *point + Point::new(1, 1);
-        let sw = *point + Point::new(-1, 1);
-
-        ((cw.get(nw) == Some('M') && cw.get(se) == Some('S'))
-            || (cw.get(nw) == Some('S') && cw.get(se) == Some('M')))
-            && ((cw.get(sw) == Some('M') && cw.get(ne) == Some('S'))
-                || (cw.get(sw) == Some('S') && cw.get(ne) == Some('M')))
     }
 }
 
+/// Two `MAS` crossing diagonally at the `A`
+pub static X_MAS: Lazy<Template> =
+    Lazy::new(|| "M.S/.A./M.S".parse().expect("X-MAS template to be valid"));
+
 pub struct Crossword(Vec<Vec<char>>);
 
 impl Crossword {
-    const DIRS: Lazy<[Point; 8]> = Lazy::new(|| {
-        [
-            Point::new(1, 0),
-            Point::new(0, 1),
-            Point::new(-1, 0),
-            Point::new(0, -1),
-            Point::new(1, 1),
-            Point::new(-1, 1),
-            Point::new(-1, -1),
-            Point::new(1, -1),
-        ]
-    });
-
-    fn find_xs(&self, point: Point) -> Vec<Point> {
-        let points = match self.get(point) {
-            Some('X') => vec![point],
-            Some('M') => Self::DIRS.iter().map(|p| point + *p).collect(),
-            Some('A') => Self::DIRS.iter().map(|p| point + *p * 2).collect(),
-            Some('S') => Self::DIRS.iter().map(|p| point + *p * 3).collect(),
-            _ => vec![],
-        };
+    const DIRS: [Point; 8] = [
+        Point::new(1, 0),
+        Point::new(0, 1),
+        Point::new(-1, 0),
+        Point::new(0, -1),
+        Point::new(1, 1),
+        Point::new(-1, 1),
+        Point::new(-1, -1),
+        Point::new(1, -1),
+    ];
+
+    /// Compass names of [`Self::DIRS`], with y increasing to the south
+    const DIR_NAMES: [&'static str; 8] = ["E", "S", "W", "N", "SE", "SW", "NW", "NE"];
+
+    /// Every occurrence of each of `words`, in any of the eight directions.
+    /// A word reading the same both ways is found once from each end, and a
+    /// single letter only once.
+    pub fn search<'w>(&self, words: &[&'w str]) -> Vec<WordMatch<'w>> {
+        let mut matches = vec![];
+        for start in self.points() {
+            for word in words {
+                let dirs = match word.chars().count() {
+                    0 => &[][..],
+                    1 => &Self::DIRS[..1],
+                    _ => &Self::DIRS[..],
+                };
+                matches.extend(
+                    dirs.iter()
+                        .filter(|dir| self.has_word(word, 0, start, **dir))
+                        .map(|dir| WordMatch {
+                            word,
+                            start,
+                            dir: *dir,
+                        }),
+                );
+            }
+        }
+        matches
+    }
 
-        points
-            .into_iter()
-            .filter(|p| self.get(*p) == Some('X'))
-            .collect()
+    /// Every placement of `template` in any of its orientations
+    /// ([`Template::orientations`]), each distinct placement found once
+    pub fn find_template(&self, template: &Template) -> Vec<TemplateMatch> {
+        let mut matches = vec![];
+        for orientation in template.orientations() {
+            for origin in self.points() {
+                let fits = orientation
+                    .letters()
+                    .all(|(offset, c)| self.get(origin + offset) == Some(c));
+                if fits {
+                    matches.push(TemplateMatch {
+                        origin,
+                        template: orientation.clone(),
+                    });
+                }
+            }
+        }
+        matches
     }
 
-    fn words_from_point(&self, word: &str, point: Point) -> usize {
-        Self::DIRS
-            .into_iter()
-            .filter(|delta| {
-                let has_word = self.has_word(word, 0, point, *delta);
-                has_word
-            })
-            .count()
+    /// Draw the grid with the letters at `found` highlighted and the rest
+    /// dimmed, or replaced with `.` without color
+    pub fn render(&self, found: impl IntoIterator<Item = Point>, color: bool) -> String {
+        let found: HashSet<Point> = found.into_iter().collect();
+        let mut out = String::new();
+
+        for (y, row) in self.0.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                let hit = found.contains(&Point::new(x as isize, y as isize));
+                match (color, hit) {
+                    (true, true) => write!(out, "{}{}{}", ansi::BOLD, c, ansi::RESET).unwrap(),
+                    (true, false) => write!(out, "{}{}{}", ansi::DIM, c, ansi::RESET).unwrap(),
+                    (false, true) => out.push(*c),
+                    (false, false) => out.push('.'),
+                }
+            }
+            out.push('\n');
+        }
+
+        out
     }
 
     fn has_word(&self, word: &str, word_idx: usize, point: Point, delta: Point) -> bool {
@@ -147,7 +167,7 @@ impl Crossword {
         self.has_word(word, word_idx + 1, point + delta, delta)
     }
 
-    fn get(&self, idx: Point) -> Option<char> {
+    pub fn get(&self, idx: Point) -> Option<char> {
         if idx.x < 0 || idx.y < 0 {
             return None;
         }
@@ -157,7 +177,7 @@ impl Crossword {
         row.get(x).copied()
     }
 
-    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
         self.0.iter().enumerate().flat_map(|(col_i, row)| {
             row.iter()
                 .enumerate()
@@ -175,6 +195,171 @@ impl<'a> SolutionInput<'a> for Crossword {
     }
 }
 
+/// Word found in a [`Crossword`], reading from `start` one step of `dir` at a
+/// time
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct WordMatch<'w> {
+    pub word: &'w str,
+    pub start: Point,
+    pub dir: Point,
+}
+
+impl WordMatch<'_> {
+    /// Points covered by the word's letters
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..self.word.chars().count()).map(|i| self.start + self.dir * i as isize)
+    }
+}
+
+impl fmt::Display for WordMatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dir = Crossword::DIRS
+            .iter()
+            .position(|dir| *dir == self.dir)
+            .map_or("?", |i| Crossword::DIR_NAMES[i]);
+        write!(
+            f,
+            "{} at ({},{}) going {}",
+            self.word, self.start.x, self.start.y, dir
+        )
+    }
+}
+
+/// Small grid of letters to look for in a [`Crossword`], where `.` matches
+/// anything. Parsed from rows separated by `/` or newlines, e.g.
+/// `M.S/.A./M.S`, dropping border rows and columns of only wildcards so that
+/// each placement of its letters has one origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Vec<Option<char>>>);
+
+impl Template {
+    /// The template in each of its rotations and reflections, without
+    /// duplicates from any symmetry
+    pub fn orientations(&self) -> Vec<Template> {
+        let mut orientations: Vec<Template> = vec![];
+        let mut rotated = self.clone();
+        for _ in 0..4 {
+            for orientation in [rotated.reflect(), rotated.clone()] {
+                if !orientations.contains(&orientation) {
+                    orientations.push(orientation);
+                }
+            }
+            rotated = rotated.rotate();
+        }
+        orientations
+    }
+
+    /// Offset of each letter from the top left, skipping wildcards
+    pub fn letters(&self) -> impl Iterator<Item = (Point, char)> + '_ {
+        self.0.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(x, c)| Some((Point::new(x as isize, y as isize), (*c)?)))
+        })
+    }
+
+    /// Quarter turn clockwise
+    fn rotate(&self) -> Self {
+        let (rows, cols) = (self.0.len(), self.0[0].len());
+        Template(
+            (0..cols)
+                .map(|x| (0..rows).rev().map(|y| self.0[y][x]).collect())
+                .collect(),
+        )
+    }
+
+    /// Mirror left to right
+    fn reflect(&self) -> Self {
+        Template(
+            self.0
+                .iter()
+                .map(|row| row.iter().rev().copied().collect())
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<Vec<Option<char>>> = s
+            .trim()
+            .split(['/', '\n'])
+            .map(|row| {
+                row.trim()
+                    .chars()
+                    .map(|c| if c == '.' { None } else { Some(c) })
+                    .collect()
+            })
+            .collect();
+
+        if rows[0].is_empty() || rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(TemplateError(s.to_string()));
+        }
+        if rows.iter().flatten().all(Option::is_none) {
+            return Err(TemplateError(s.to_string()));
+        }
+
+        let has_letter = |row: &Vec<Option<char>>| row.iter().any(Option::is_some);
+        let top = rows.iter().position(has_letter).unwrap();
+        let bottom = rows.iter().rposition(has_letter).unwrap();
+        let rows = &rows[top..=bottom];
+        let has_letter = |x: &usize| rows.iter().any(|row| row[*x].is_some());
+        let left = (0..rows[0].len()).find(has_letter).unwrap();
+        let right = (0..rows[0].len()).rfind(has_letter).unwrap();
+
+        Ok(Template(
+            rows.iter().map(|row| row[left..=right].to_vec()).collect(),
+        ))
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.0.iter().enumerate() {
+            if y > 0 {
+                f.write_char('/')?;
+            }
+            for c in row {
+                f.write_char(c.unwrap_or('.'))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid template '{0}', expected rows of equal length with at least one letter, e.g. M.S/.A./M.S")]
+pub struct TemplateError(String);
+
+/// Placement of a [`Template`], in one of its orientations, with its top left
+/// at `origin`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateMatch {
+    pub origin: Point,
+    pub template: Template,
+}
+
+impl TemplateMatch {
+    /// Points covered by the template's letters
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        self.template
+            .letters()
+            .map(|(offset, _)| self.origin + offset)
+    }
+}
+
+impl fmt::Display for TemplateMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at ({},{})",
+            self.template, self.origin.x, self.origin.y
+        )
+    }
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub struct Point {
     x: isize,
@@ -182,7 +367,7 @@ pub struct Point {
 }
 
 impl Point {
-    pub fn new(x: isize, y: isize) -> Self {
+    pub const fn new(x: isize, y: isize) -> Self {
         Self { x, y }
     }
 }
@@ -211,9 +396,13 @@ impl Mul<isize> for Point {
 
 #[cfg(test)]
 mod test {
-    use crate::day::d4::Point;
+    use crate::{
+        day_test,
+        harness::{Part, SolutionInput},
+        render::ansi,
+    };
 
-    use super::Crossword;
+    use super::{Crossword, Point, Template, TemplateMatch, WordMatch, D4, X_MAS};
 
     #[test]
     fn test_has_word() {
@@ -246,6 +435,185 @@ mod test {
             vec!['.', '.', '.', 'S', '.', '.', '.'],
         ]);
 
-        assert_eq!(2, crossword.words_from_point("XMAS", Point::new(3, 3)));
+        let matches = crossword.search(&["XMAS"]);
+        assert_eq!(2, matches.len());
+        assert!(matches.iter().all(|m| m.start == Point::new(3, 3)));
+    }
+
+    #[test]
+    fn test_search() {
+        let crossword = Crossword::read(
+            "\
+..X...
+.SAMX.
+.A..A.
+XMAS.S
+.X....
+"
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let matches = crossword.search(&["XMAS", "SAM", "Q", ""]);
+        let found: Vec<String> = matches.iter().map(WordMatch::to_string).collect();
+        assert_eq!(
+            vec![
+                "XMAS at (2,0) going SE",
+                "SAM at (1,1) going E",
+                "SAM at (1,1) going S",
+                "XMAS at (4,1) going W",
+                "XMAS at (0,3) going E",
+                "SAM at (3,3) going W",
+                "SAM at (5,3) going NW",
+                "XMAS at (1,4) going N",
+            ],
+            found
+        );
+
+        let xmas: Vec<WordMatch> = matches.into_iter().filter(|m| m.word == "XMAS").collect();
+        assert_eq!(
+            "\
+..X...
+.SAMX.
+.A..A.
+XMAS.S
+.X....
+",
+            crossword.render(xmas.iter().flat_map(WordMatch::points), false)
+        );
+    }
+
+    #[test]
+    fn test_render_example() {
+        let crossword = Crossword::read(EXAMPLE.as_bytes()).unwrap();
+        let matches = crossword.search(&["XMAS"]);
+
+        assert_eq!(18, matches.len());
+        assert_eq!(
+            "\
+....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+",
+            crossword.render(matches.iter().flat_map(WordMatch::points), false)
+        );
+        assert!(crossword
+            .render(matches.iter().flat_map(WordMatch::points), true)
+            .contains(ansi::DIM));
+    }
+
+    #[test]
+    fn test_template_orientations() {
+        // X-MAS is symmetric, so only has four distinct orientations
+        let orientations: Vec<String> = X_MAS
+            .orientations()
+            .iter()
+            .map(Template::to_string)
+            .collect();
+        assert_eq!(
+            vec!["S.M/.A./S.M", "M.S/.A./M.S", "M.M/.A./S.S", "S.S/.A./M.M"],
+            orientations
+        );
+
+        let t: Template = "XM/A.".parse().unwrap();
+        assert_eq!(8, t.orientations().len());
+        assert!(t.orientations().contains(&"AX/.M".parse().unwrap()));
+
+        // wildcard borders are dropped
+        assert_eq!("XM", "XM/..".parse::<Template>().unwrap().to_string());
+        assert_eq!(
+            "A./.M",
+            "..../.A../..M./...."
+                .parse::<Template>()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(4, "XM/..".parse::<Template>().unwrap().orientations().len());
+
+        assert!("AB/C".parse::<Template>().is_err());
+        assert!("./.".parse::<Template>().is_err());
+        assert!("".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn test_find_template() {
+        let crossword = Crossword::read(EXAMPLE.as_bytes()).unwrap();
+        let matches = crossword.find_template(&X_MAS);
+
+        assert_eq!(9, matches.len());
+        assert_eq!(
+            "\
+.M.S......
+..A..MSMS.
+.M.S.MAA..
+..A.ASMSM.
+.M.S.M....
+..........
+S.S.S.S.S.
+.A.A.A.A..
+M.M.M.M.M.
+..........
+",
+            crossword.render(matches.iter().flat_map(TemplateMatch::points), false)
+        );
+
+        // other shapes need no new code, e.g. a plus of MASes
+        let crossword = Crossword::read(".S.S\nSAMA\n.M.M\n".as_bytes()).unwrap();
+        let plus: Template = ".M./MAS/.S.".parse().unwrap();
+        let found: Vec<String> = crossword
+            .find_template(&plus)
+            .iter()
+            .map(TemplateMatch::to_string)
+            .collect();
+        assert_eq!(vec![".S./SAM/.M. at (0,0)"], found);
+    }
+
+    #[test]
+    fn test_find_padded_template() {
+        // the padding used to let two orientations match the same letters
+        let template: Template = "XM/..".parse().unwrap();
+        for (grid, expected) in [
+            ("..\nXM\n", "XM at (0,1)"),
+            ("XM\n..\n", "XM at (0,0)"),
+            ("M.\nX.\n", "M/X at (0,0)"),
+        ] {
+            let crossword = Crossword::read(grid.as_bytes()).unwrap();
+            let found: Vec<String> = crossword
+                .find_template(&template)
+                .iter()
+                .map(TemplateMatch::to_string)
+                .collect();
+            assert_eq!(vec![expected], found);
+        }
+
+        let crossword = Crossword::read("XM\nA.\n".as_bytes()).unwrap();
+        let template: Template = "..../.XM./.A../....".parse().unwrap();
+        assert_eq!(1, crossword.find_template(&template).len());
+    }
+
+    const EXAMPLE: &str = "\
+MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D4, Part::One, EXAMPLE, 18);
+        day_test!(D4, Part::Two, EXAMPLE, 9);
     }
 }
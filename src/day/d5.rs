@@ -1,11 +1,12 @@
-use std::array;
+use std::{collections::BTreeMap, fmt};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
+use thiserror::Error;
 use update::Update;
 
 use crate::{
-    harness::{iter, Day, SolutionInput},
+    harness::{iter, Day, InputError, SolutionInput},
     parse::parse_u32,
 };
 
@@ -36,14 +37,14 @@ mod p1 {
                 .updates
                 .iter()
                 .filter(|u| update::is_in_order(u, &input.rules))
-                .map(|u| update::midpoint(u))
+                .map(update::midpoint)
                 .sum())
         }
     }
 }
 
 mod p2 {
-    use crate::harness::Solution;
+    use crate::harness::{Error, Solution};
 
     use super::{update, Input};
 
@@ -54,52 +55,162 @@ mod p2 {
         type Output = u32;
 
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
-            Ok(input
+            input
                 .updates
                 .iter()
                 .filter(|u| !update::is_in_order(u, &input.rules))
-                .map(|u| update::reorder(u, &input.rules))
-                .map(|u| update::midpoint(&u))
-                .sum())
+                .map(|u| {
+                    let reordered = update::reorder(u, &input.rules)
+                        .map_err(|err| Error::SolutionError(Box::new(err)))?;
+                    Ok(update::midpoint(&reordered.pages))
+                })
+                .sum()
         }
     }
 }
 
-const PAGES: usize = 100;
-
 type Rule = (u32, u32);
 
+/// Why the pages of an update couldn't be put in order
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OrderError {
+    /// Each page must precede the next, and the last the first
+    #[error("ordering rules form a cycle: {}", Self::cycle_str(pages))]
+    Cycle { pages: Vec<u32> },
+}
+
+impl OrderError {
+    fn cycle_str(pages: &[u32]) -> String {
+        pages
+            .iter()
+            .chain(pages.first())
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
 #[derive(Debug)]
 pub struct Input {
     rules: Vec<Rule>,
     updates: Vec<Update>,
 }
 
+impl Input {
+    /// How each update stands against the rules
+    pub fn diagnose(&self) -> impl Iterator<Item = Diagnosis<'_>> {
+        self.updates.iter().map(|u| Diagnosis {
+            update: u,
+            order: if update::is_in_order(u, &self.rules) {
+                Order::InOrder
+            } else {
+                match update::reorder(u, &self.rules) {
+                    Ok(reordered) => Order::Reordered(reordered),
+                    Err(err) => Order::Cycle(err),
+                }
+            },
+        })
+    }
+}
+
+/// Whether an update is in order, and if not how it can be put in order
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnosis<'u> {
+    pub update: &'u Update,
+    pub order: Order,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Order {
+    InOrder,
+    Reordered(update::Reordered),
+    Cycle(OrderError),
+}
+
+impl fmt::Display for Diagnosis<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pages = |pages: &[u32]| {
+            pages
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        write!(f, "{}: ", pages(self.update))?;
+        match &self.order {
+            Order::InOrder => write!(f, "in order, middle page {}", update::midpoint(self.update)),
+            Order::Reordered(reordered) => {
+                write!(
+                    f,
+                    "reordered to {}, middle page {}",
+                    pages(&reordered.pages),
+                    update::midpoint(&reordered.pages)
+                )?;
+                if let Some(ambiguous) = &reordered.ambiguous {
+                    write!(f, " (ambiguous, no rules order {})", pages(ambiguous))?;
+                }
+                Ok(())
+            }
+            Order::Cycle(err) => write!(f, "{}", err),
+        }
+    }
+}
+
 impl<'a> SolutionInput<'a> for Input {
     fn read(reader: impl std::io::BufRead + 'a) -> crate::harness::input::Result<Self> {
         let mut rules = vec![];
         let mut updates = vec![];
 
-        static RULE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+)\|(\d+)").unwrap());
-        static UPDATE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+)").unwrap());
+        static RULE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)\|(\d+)$").unwrap());
+        static UPDATE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d+(,\d+)*$").unwrap());
+
+        let mut sections = iter::sections(reader);
+        let rules_section = sections.next().transpose()?;
+        let updates_section = sections.next().transpose()?;
+        if let Some(section) = sections.next().transpose()? {
+            return Err(InputError::InvalidInput {
+                msg: format!("Unexpected section on line {}", section.line_num),
+                source: None,
+            });
+        }
 
-        for line in iter::lines(reader) {
-            let line = line?;
-            if line.is_empty() {
-                continue;
+        if let Some(section) = rules_section {
+            for (idx, line) in section.lines.iter().enumerate() {
+                let line_num = section.line_num + idx;
+                let line = line.trim();
+                let caps = RULE_RE.captures(line).ok_or_else(|| {
+                    let msg = if UPDATE_RE.is_match(line) {
+                        format!(
+                            "Expected rules before updates, but found update '{}' on line {}",
+                            line, line_num
+                        )
+                    } else {
+                        format!("Bad rule on line {}: '{}'", line_num, line)
+                    };
+                    InputError::InvalidInput { msg, source: None }
+                })?;
+                let (_, [x, y]) = caps.extract();
+                rules.push((parse_u32(x)?, parse_u32(y)?));
             }
+        }
 
-            if let Some(caps) = RULE_RE.captures(&line) {
-                let (_, [x, y]) = caps.extract();
-                let x = parse_u32(x)?;
-                let y = parse_u32(y)?;
-                rules.push((x, y));
-            } else {
-                let mut update = vec![];
-                for (_, [x]) in UPDATE_RE.captures_iter(&line).map(|c| c.extract()) {
-                    update.push(parse_u32(x)?);
+        if let Some(section) = updates_section {
+            for (idx, line) in section.lines.iter().enumerate() {
+                let line_num = section.line_num + idx;
+                let line = line.trim();
+                if !UPDATE_RE.is_match(line) {
+                    let msg = if RULE_RE.is_match(line) {
+                        format!(
+                            "Expected updates after rules, but found rule '{}' on line {}",
+                            line, line_num
+                        )
+                    } else {
+                        format!("Bad update on line {}: '{}'", line_num, line)
+                    };
+                    return Err(InputError::InvalidInput { msg, source: None });
                 }
-                updates.push(update);
+                updates.push(line.split(',').map(parse_u32).collect::<Result<_, _>>()?);
             }
         }
 
@@ -121,37 +232,97 @@ fn dependency_map(update: &Update, all_rules: &Vec<Rule>) -> PageMap<PageSet> {
     map
 }
 
-mod update {
-    use super::{dependency_map, PageSet, Rule};
+pub mod update {
+    use super::{dependency_map, OrderError, PageMap, PageSet, Rule};
 
     pub type Update = Vec<u32>;
 
-    pub fn reorder(update: &Update, all_rules: &Vec<Rule>) -> Update {
+    /// An update put in order
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Reordered {
+        pub pages: Update,
+        /// Pages no rule orders relative to each other, if there were any, so
+        /// `pages` is only one of several valid orders
+        pub ambiguous: Option<Vec<u32>>,
+    }
+
+    /// Order the pages of `update` by the rules which apply to them, with
+    /// Kahn's algorithm. Fails if the rules form a cycle.
+    pub fn reorder(update: &Update, all_rules: &Vec<Rule>) -> Result<Reordered, OrderError> {
         let rules = dependency_map(update, all_rules);
-        let mut to_explore: Vec<_> = update.iter().copied().rev().collect();
+
+        // page -> number of preceding pages not yet placed
+        let mut waiting: PageMap<u32> = PageMap::new();
+        for (page, must_precede) in rules.iter() {
+            waiting.add(&page, must_precede.len());
+        }
+
+        let mut ready: Vec<u32> = waiting
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(page, _)| page)
+            .collect();
+        let mut ambiguous = None;
         let mut in_order = vec![];
-        let mut added = PageSet::new();
 
-        while let Some(page) = to_explore.pop() {
-            if added.has(&page) {
-                continue;
+        while let Some(page) = ready.pop() {
+            if !ready.is_empty() && ambiguous.is_none() {
+                let mut pages = ready.clone();
+                pages.push(page);
+                pages.sort_unstable();
+                ambiguous = Some(pages);
             }
 
-            let must_precede = rules.get(&page).unwrap();
-            let missing = must_precede.difference(&added);
-
-            if missing.len() > 0 {
-                to_explore.push(page);
-                for p in missing.iter() {
-                    to_explore.push(p);
+            in_order.push(page);
+            waiting.del(&page);
+            for (next, must_precede) in rules.iter() {
+                if must_precede.has(&page) {
+                    let count = waiting.get_mut(&next).unwrap();
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(next);
+                    }
                 }
-            } else {
-                in_order.push(page);
-                added.add(page);
             }
         }
 
-        in_order
+        if !waiting.is_empty() {
+            let remaining = waiting.iter().map(|(page, _)| page).collect();
+            return Err(OrderError::Cycle {
+                pages: find_cycle(&rules, &remaining),
+            });
+        }
+
+        Ok(Reordered {
+            pages: in_order,
+            ambiguous,
+        })
+    }
+
+    /// A cycle among `remaining`, the pages Kahn's algorithm couldn't place,
+    /// each of which must be preceded by another of them. Pages are in rule
+    /// order, starting from the lowest.
+    fn find_cycle(rules: &PageMap<PageSet>, remaining: &PageSet) -> Vec<u32> {
+        let mut path: Vec<u32> = vec![];
+        let mut page = remaining.iter().next().expect("a page to remain");
+
+        while !path.contains(&page) {
+            path.push(page);
+            page = rules
+                .get(&page)
+                .unwrap()
+                .iter()
+                .find(|pre| remaining.has(pre))
+                .expect("every remaining page to have a remaining predecessor");
+        }
+
+        // the path walks backwards from each page to one preceding it
+        let start = path.iter().position(|p| *p == page).unwrap();
+        let mut cycle: Vec<u32> = path.split_off(start);
+        cycle.reverse();
+        let lowest = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+        cycle.rotate_left(lowest);
+        cycle
     }
 
     pub fn is_in_order(update: &Update, all_rules: &Vec<Rule>) -> bool {
@@ -175,98 +346,186 @@ mod update {
     }
 }
 
+/// Sparse bitset of page numbers: the nonzero words of 64 pages, keyed by
+/// their index, so equal sets have equal words
+#[derive(Clone, Default, PartialEq)]
+struct Bits(BTreeMap<u32, u64>);
+
+impl Bits {
+    fn split(page: u32) -> (u32, u64) {
+        (page / 64, 1 << (page % 64))
+    }
+
+    fn add(&mut self, page: u32) {
+        let (word, bit) = Self::split(page);
+        *self.0.entry(word).or_default() |= bit;
+    }
+
+    fn del(&mut self, page: u32) {
+        let (word, bit) = Self::split(page);
+        if let Some(w) = self.0.get_mut(&word) {
+            *w &= !bit;
+            if *w == 0 {
+                self.0.remove(&word);
+            }
+        }
+    }
+
+    fn has(&self, page: u32) -> bool {
+        let (word, bit) = Self::split(page);
+        self.0.get(&word).is_some_and(|w| w & bit != 0)
+    }
+
+    fn len(&self) -> u32 {
+        self.0.values().map(|w| w.count_ones()).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn intersect_count(&self, other: &Bits) -> u32 {
+        let (fewer, more) = if self.0.len() <= other.0.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        fewer
+            .0
+            .iter()
+            .filter_map(|(i, a)| Some((a & more.0.get(i)?).count_ones()))
+            .sum()
+    }
+
+    /// Pages in `self` but not `other`
+    fn difference(&self, other: &Bits) -> Bits {
+        let words = self.0.iter().filter_map(|(i, a)| {
+            let word = a & !other.0.get(i).unwrap_or(&0);
+            (word != 0).then_some((*i, word))
+        });
+        Bits(words.collect())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.0.iter().flat_map(|(i, word)| {
+            let (i, mut word) = (*i, *word);
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros();
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Values keyed by page number, only storing the pages added. Unlike
+/// [`PageSet`], its set operations go entry by entry rather than a word at a
+/// time.
 #[derive(Clone, PartialEq)]
-pub struct PageMap<T>([Option<T>; PAGES]);
+pub struct PageMap<T>(BTreeMap<u32, T>);
+
+impl<T> Default for PageMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl<T> PageMap<T> {
     pub fn new() -> Self {
-        Self(array::from_fn(|_| None))
+        Self(BTreeMap::new())
     }
 
     pub fn add(&mut self, page: &u32, val: T) {
-        self.0[*page as usize].replace(val);
+        self.0.insert(*page, val);
     }
 
     pub fn del(&mut self, page: &u32) {
-        self.0[*page as usize].take();
+        self.0.remove(page);
     }
 
     pub fn get(&self, page: &u32) -> Option<&T> {
-        self.0[*page as usize].as_ref()
+        self.0.get(page)
     }
 
     pub fn get_mut(&mut self, page: &u32) -> Option<&mut T> {
-        self.0[*page as usize].as_mut()
+        self.0.get_mut(page)
     }
 
     pub fn has(&self, page: &u32) -> bool {
-        self.get(page).is_some()
+        self.0.contains_key(page)
     }
 
     pub fn len(&self) -> u32 {
-        self.0.iter().filter(|t| t.is_some()).count() as u32
+        self.0.len() as u32
     }
 
-    pub fn intersect_count(&self, other: &PageMap<T>) -> u32 {
-        let mut count = 0;
-
-        for p in 0..PAGES {
-            if self.has(&(p as u32)) && other.has(&(p as u32)) {
-                count += 1;
-            }
-        }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 
-        count
+    pub fn intersect_count(&self, other: &PageMap<T>) -> u32 {
+        let (fewer, more) = if self.0.len() <= other.0.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        fewer.0.keys().filter(|page| more.has(page)).count() as u32
     }
 
+    /// Entries of `self` whose page isn't in `other`, or has a different
+    /// value there
     pub fn difference(&self, other: &PageMap<T>) -> PageMap<T>
     where
         T: PartialEq + Clone,
     {
-        let mut new = PageMap::new();
-
-        for (page, item) in self.0.iter().enumerate() {
-            let page = page as u32;
-            if item.is_some() && item.as_ref() != other.get(&page) {
-                new.add(&page, item.as_ref().unwrap().clone());
-            }
-        }
-
-        new
+        PageMap(
+            self.0
+                .iter()
+                .filter(|(page, val)| other.get(page) != Some(*val))
+                .map(|(page, val)| (*page, val.clone()))
+                .collect(),
+        )
     }
 
+    /// Entries in page order
     pub fn iter(&self) -> impl Iterator<Item = (u32, &T)> + '_ {
-        self.0
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, entry)| entry.as_ref().map(|t| (idx as u32, t)))
+        self.0.iter().map(|(page, val)| (*page, val))
     }
 }
 
-#[derive(Clone, PartialEq)]
-pub struct PageSet(PageMap<()>);
+/// Set of page numbers, intersected and differenced a word of 64 pages at a
+/// time
+#[derive(Clone, Default, PartialEq)]
+pub struct PageSet(Bits);
 
 impl PageSet {
     pub fn new() -> Self {
-        Self(PageMap::new())
+        Self(Bits::default())
     }
 
     pub fn add(&mut self, page: u32) {
-        self.0.add(&page, ());
+        self.0.add(page);
     }
 
     pub fn del(&mut self, page: &u32) {
-        self.0.del(page);
+        self.0.del(*page);
     }
 
     pub fn has(&self, page: &u32) -> bool {
-        self.0.has(page)
+        self.0.has(*page)
     }
 
     pub fn len(&self) -> u32 {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn intersect_count(&self, other: &PageSet) -> u32 {
         self.0.intersect_count(&other.0)
     }
@@ -276,7 +535,7 @@ impl PageSet {
     }
 
     pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
-        self.0.iter().map(|(idx, _)| idx)
+        self.0.iter()
     }
 }
 
@@ -291,3 +550,267 @@ impl FromIterator<u32> for PageSet {
         p
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        day_test,
+        harness::{testing, Day, Params, Part, SolutionInput},
+    };
+
+    use super::{update, Input, OrderError, PageMap, PageSet, D5};
+
+    const EXAMPLE: &str = "\
+47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
+    #[test]
+    fn test_sections_out_of_order() {
+        let swapped = "75,47,61\n\n47|53\n";
+        let err = Input::read(swapped.as_bytes()).unwrap_err();
+        assert_eq!(
+            "failed to parse input: Expected rules before updates, but found update '75,47,61' on line 1",
+            err.to_string()
+        );
+
+        let rules_only = "47|53\n97|13\n\n75,47,61\n97|61\n";
+        let err = Input::read(rules_only.as_bytes()).unwrap_err();
+        assert_eq!(
+            "failed to parse input: Expected updates after rules, but found rule '97|61' on line 5",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_example() {
+        day_test!(D5, Part::One, EXAMPLE, 143);
+        day_test!(D5, Part::Two, EXAMPLE, 123);
+    }
+
+    #[test]
+    fn test_reorder_is_in_order() {
+        testing::check(
+            "reordered updates are in order",
+            |rng| testing::page_ordering(rng, 9, 5, 100),
+            |input| {
+                let input = Input::read(input.as_bytes()).unwrap();
+                input.updates.iter().all(|u| {
+                    let reordered = update::reorder(u, &input.rules).unwrap();
+                    update::is_in_order(&reordered.pages, &input.rules)
+                })
+            },
+        );
+    }
+
+    #[test]
+    fn test_reorder_ambiguity() {
+        testing::check(
+            "only pages without rules between them are ambiguous",
+            |rng| testing::page_ordering(rng, 9, 5, 100),
+            |input| {
+                let input = Input::read(input.as_bytes()).unwrap();
+                let has_rule = |x: u32, y: u32| {
+                    input
+                        .rules
+                        .iter()
+                        .any(|rule| *rule == (x, y) || *rule == (y, x))
+                };
+                input.updates.iter().all(|u| {
+                    match update::reorder(u, &input.rules).unwrap() {
+                        // the order is only unique if each page has to come
+                        // right before the next
+                        update::Reordered {
+                            pages,
+                            ambiguous: None,
+                        } => pages.windows(2).all(|w| has_rule(w[0], w[1])),
+                        update::Reordered {
+                            ambiguous: Some(pages),
+                            ..
+                        } => {
+                            pages.len() > 1
+                                && pages
+                                    .iter()
+                                    .enumerate()
+                                    .all(|(i, x)| pages[i + 1..].iter().all(|y| !has_rule(*x, *y)))
+                        }
+                    }
+                })
+            },
+        );
+    }
+
+    #[test]
+    fn test_reorder_errors() {
+        let rules = vec![(3, 1), (1, 2), (2, 3), (3, 4), (5, 4)];
+
+        let err = update::reorder(&vec![4, 2, 3, 1], &rules).unwrap_err();
+        assert_eq!(
+            OrderError::Cycle {
+                pages: vec![1, 2, 3]
+            },
+            err
+        );
+        assert_eq!(
+            "ordering rules form a cycle: 1 -> 2 -> 3 -> 1",
+            err.to_string()
+        );
+        assert!(!update::is_in_order(&vec![1, 2, 3], &rules));
+
+        let reordered = update::reorder(&vec![4, 5, 1], &rules).unwrap();
+        assert_eq!(Some(vec![1, 5]), reordered.ambiguous);
+        assert!(update::is_in_order(&reordered.pages, &rules));
+        assert_eq!(
+            Ok(update::Reordered {
+                pages: vec![2, 3, 4],
+                ambiguous: None
+            }),
+            update::reorder(&vec![4, 2, 3], &rules)
+        );
+        assert_eq!(
+            Ok(update::Reordered {
+                pages: vec![5, 4],
+                ambiguous: None
+            }),
+            update::reorder(&vec![4, 5], &rules)
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_orders_are_solved() {
+        // 4 and 5 only have to follow 1, so either could be in the middle
+        let input = "1|4
+1|5
+
+4,5,1
+";
+        let reordered = update::reorder(&vec![4, 5, 1], &vec![(1, 4), (1, 5)]).unwrap();
+        assert_eq!(Some(vec![4, 5]), reordered.ambiguous);
+        day_test!(D5, Part::Two, input, reordered.pages[1]);
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let input = Input::read("1|2\n1|3\n4|5\n5|4\n\n1,2\n3,2,1\n4,5\n".as_bytes()).unwrap();
+        let diagnoses: Vec<String> = input.diagnose().map(|d| d.to_string()).collect();
+        assert_eq!(
+            vec![
+                "1,2: in order, middle page 2",
+                "3,2,1: reordered to 1,3,2, middle page 3 (ambiguous, no rules order 2,3)",
+                "4,5: ordering rules form a cycle: 4 -> 5 -> 4",
+            ],
+            diagnoses
+        );
+    }
+
+    #[test]
+    fn test_cycle_errors() {
+        let input = "1|2\n2|3\n3|1\n\n3,2,1\n";
+        let err = D5::run_with(Part::Two, input.as_bytes(), &Params::new()).unwrap_err();
+        assert_eq!(
+            "ordering rules form a cycle: 1 -> 2 -> 3 -> 1",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_large_pages() {
+        let input = "100|7000\n7000|65\n64|100\n\n65,100,64,7000\n100,7000\n";
+        day_test!(D5, Part::One, input, 7000);
+        day_test!(D5, Part::Two, input, 7000);
+
+        // storage doesn't grow with the page numbers
+        let input = "4294967295|1\n1|4294967294\n40000000|1\n\n4294967294,1,4294967295\n";
+        day_test!(D5, Part::One, input, 0);
+        day_test!(D5, Part::Two, input, 1);
+    }
+
+    #[test]
+    fn test_page_sets() {
+        testing::check(
+            "page sets match BTreeSets",
+            |rng| {
+                let pages = |rng: &mut testing::Rng| -> Vec<u32> {
+                    (0..rng.index(20))
+                        .map(|_| match rng.index(3) {
+                            0 => u32::MAX - rng.range(0, 300) as u32,
+                            _ => rng.range(0, 300) as u32,
+                        })
+                        .collect()
+                };
+                (pages(rng), pages(rng), pages(rng))
+            },
+            |(a, b, removed)| {
+                let (mut set_a, set_b): (PageSet, PageSet) =
+                    (a.iter().copied().collect(), b.iter().copied().collect());
+                let (mut tree_a, tree_b): (BTreeSet<u32>, BTreeSet<u32>) =
+                    (a.iter().copied().collect(), b.iter().copied().collect());
+                for page in removed {
+                    set_a.del(page);
+                    tree_a.remove(page);
+                }
+
+                let expected: Vec<u32> = tree_a.difference(&tree_b).copied().collect();
+                let difference = set_a.difference(&set_b);
+                set_a.len() as usize == tree_a.len()
+                    && set_a.is_empty() == tree_a.is_empty()
+                    && set_a.iter().eq(tree_a.iter().copied())
+                    && set_a.intersect_count(&set_b) as usize
+                        == tree_a.intersection(&tree_b).count()
+                    && difference.iter().eq(expected.iter().copied())
+                    && difference == expected.into_iter().collect()
+            },
+        );
+    }
+
+    #[test]
+    fn test_page_map_difference() {
+        let mut a = PageMap::new();
+        let mut b = PageMap::new();
+        for (page, val) in [(1, 'a'), (200, 'b'), (5000, 'c'), (u32::MAX, 'd')] {
+            a.add(&page, val);
+        }
+        b.add(&200, 'b');
+        b.add(&5000, 'x');
+        b.add(&9, 'y');
+
+        let diff: Vec<(u32, char)> = a.difference(&b).iter().map(|(p, v)| (p, *v)).collect();
+        assert_eq!(vec![(1, 'a'), (5000, 'c'), (u32::MAX, 'd')], diff);
+        assert_eq!(2, a.intersect_count(&b));
+
+        a.del(&5000);
+        a.del(&u32::MAX);
+        b.del(&9);
+        b.del(&5000);
+        b.add(&1, 'a');
+        assert!(a == b);
+    }
+}
@@ -36,11 +36,11 @@ pub mod p1 {
 }
 
 pub mod p2 {
-    use std::{collections::HashSet, iter};
+    use std::{iter, num::NonZeroUsize, thread};
 
     use crate::harness::Solution;
 
-    use super::{Map, Position};
+    use super::{grid::Point, Direction, Map, MapItem, Position};
 
     pub struct P2;
 
@@ -49,34 +49,192 @@ pub mod p2 {
         type Output = usize;
 
         fn solve(mut input: Self::Input) -> crate::harness::Result<Self::Output> {
-            let possible_placements: HashSet<_> = iter::from_fn(|| input.move_guard())
-                .map(|(point, _)| point)
-                .skip(1) // do not place at the guard's starting position
-                .collect();
-
-            Ok(possible_placements
-                .iter()
-                .filter(|point| {
-                    input.reset();
-                    input.place_obstacle(&point);
-                    let creates_cycle = has_cycle(&mut input);
-                    creates_cycle
+            let jumps = Jumps::new(&input);
+            let candidates = candidates(&mut input);
+
+            let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+            let chunk_size = candidates.len().div_ceil(threads).max(1);
+
+            Ok(thread::scope(|scope| {
+                let workers: Vec<_> = candidates
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        let jumps = &jumps;
+                        scope.spawn(move || {
+                            let mut seen = Seen::new(jumps.cells.len());
+                            chunk
+                                .iter()
+                                .filter(|(obstacle, start)| {
+                                    jumps.loops(*start, *obstacle, &mut seen)
+                                })
+                                .count()
+                        })
+                    })
+                    .collect();
+
+                workers
+                    .into_iter()
+                    .map(|worker| worker.join().expect("loop check not to panic"))
+                    .sum()
+            }))
+        }
+    }
+
+    /// Points on the guard's route where an obstacle could be placed, each
+    /// with the guard's position just before they first step onto it. Up to
+    /// there the route is unaffected by the obstacle, so a check can start
+    /// from that position rather than the guard's origin.
+    fn candidates(map: &mut Map) -> Vec<(Point, Position)> {
+        let cols = map.grid.cols();
+        let idx = |p: &Point| p.y() as usize * cols + p.x() as usize;
+        let mut visited = vec![false; cols * map.grid.rows()];
+        visited[idx(&map.guard_origin.0)] = true;
+
+        map.reset();
+        let route: Vec<Position> = iter::from_fn(|| map.move_guard()).collect();
+        route
+            .windows(2)
+            .filter_map(|pair| {
+                let (prev, (point, _)) = (pair[0], pair[1]);
+                (!visited[idx(&point)]).then(|| {
+                    visited[idx(&point)] = true;
+                    (point, prev)
                 })
-                .count())
+            })
+            .collect()
+    }
+
+    /// For each tile and direction, where the guard stops when walking until
+    /// they hit an obstacle, so a route can be followed a segment at a time
+    struct Jumps {
+        cols: usize,
+        /// Stop `(x, y)` per direction (indexed by [`dir_idx`]), or `None` if
+        /// the guard walks off the map
+        cells: Vec<[Option<(usize, usize)>; 4]>,
+    }
+
+    impl Jumps {
+        fn new(map: &Map) -> Self {
+            let (cols, rows) = (map.grid.cols(), map.grid.rows());
+            let blocked = |x: usize, y: usize| {
+                map.grid.get(&Point::new(x as isize, y as isize)) == Some(&MapItem::Obstacle)
+            };
+            let mut cells = vec![[None; 4]; cols * rows];
+
+            for x in 0..cols {
+                let mut stop = None;
+                for y in 0..rows {
+                    if blocked(x, y) {
+                        stop = Some((x, y + 1));
+                    } else {
+                        cells[y * cols + x][dir_idx(Direction::North)] = stop;
+                    }
+                }
+                let mut stop = None;
+                for y in (0..rows).rev() {
+                    if blocked(x, y) {
+                        stop = y.checked_sub(1).map(|y| (x, y));
+                    } else {
+                        cells[y * cols + x][dir_idx(Direction::South)] = stop;
+                    }
+                }
+            }
+            for y in 0..rows {
+                let mut stop = None;
+                for x in 0..cols {
+                    if blocked(x, y) {
+                        stop = Some((x + 1, y));
+                    } else {
+                        cells[y * cols + x][dir_idx(Direction::West)] = stop;
+                    }
+                }
+                let mut stop = None;
+                for x in (0..cols).rev() {
+                    if blocked(x, y) {
+                        stop = x.checked_sub(1).map(|x| (x, y));
+                    } else {
+                        cells[y * cols + x][dir_idx(Direction::East)] = stop;
+                    }
+                }
+            }
+
+            Self { cols, cells }
+        }
+
+        /// Whether the guard loops when starting from `start` with an extra
+        /// obstacle at `obstacle`. Only the positions where the guard turns
+        /// are tracked, as any loop has to include a turn.
+        fn loops(&self, start: Position, obstacle: Point, seen: &mut Seen) -> bool {
+            let (ox, oy) = (obstacle.x() as usize, obstacle.y() as usize);
+            let (mut x, mut y) = (start.0.x() as usize, start.0.y() as usize);
+            let mut dir = start.1;
+            seen.clear();
+
+            loop {
+                let stop = self.cells[y * self.cols + x][dir_idx(dir)];
+                // the extra obstacle stops the guard sooner if it's between
+                // them and the next obstacle (or the edge of the map)
+                let stop = match dir {
+                    Direction::North if x == ox && oy < y => {
+                        Some(stop.map_or((x, oy + 1), |(sx, sy)| (sx, sy.max(oy + 1))))
+                    }
+                    Direction::South if x == ox && oy > y => {
+                        Some(stop.map_or((x, oy - 1), |(sx, sy)| (sx, sy.min(oy - 1))))
+                    }
+                    Direction::West if y == oy && ox < x => {
+                        Some(stop.map_or((ox + 1, y), |(sx, sy)| (sx.max(ox + 1), sy)))
+                    }
+                    Direction::East if y == oy && ox > x => {
+                        Some(stop.map_or((ox - 1, y), |(sx, sy)| (sx.min(ox - 1), sy)))
+                    }
+                    _ => stop,
+                };
+
+                let Some((sx, sy)) = stop else {
+                    return false;
+                };
+                (x, y, dir) = (sx, sy, dir.turn());
+                if !seen.insert((y * self.cols + x) * 4 + dir_idx(dir)) {
+                    return true;
+                }
+            }
         }
     }
 
-    fn has_cycle(map: &mut Map) -> bool {
-        let mut visited: HashSet<Position> = HashSet::new();
+    fn dir_idx(dir: Direction) -> usize {
+        match dir {
+            Direction::North => 0,
+            Direction::East => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+        }
+    }
+
+    /// Set of turn states which is cleared in constant time, by bumping a
+    /// generation rather than zeroing every entry
+    struct Seen {
+        generation: u32,
+        marks: Vec<u32>,
+    }
 
-        while let Some(pos) = map.move_guard() {
-            if visited.contains(&pos) {
-                return true;
+    impl Seen {
+        fn new(cells: usize) -> Self {
+            Self {
+                generation: 0,
+                marks: vec![0; cells * 4],
             }
-            visited.insert(pos);
         }
 
-        false
+        fn clear(&mut self) {
+            self.generation += 1;
+        }
+
+        /// Returns whether `state` wasn't already in the set
+        fn insert(&mut self, state: usize) -> bool {
+            let fresh = self.marks[state] != self.generation;
+            self.marks[state] = self.generation;
+            fresh
+        }
     }
 }
 
@@ -127,11 +285,7 @@ impl Map {
     /// - the guard turning 90 degrees
     /// - the guard moving off the grid
     pub fn move_guard(&mut self) -> Option<Position> {
-        let guard_pos = if let Some(g) = self.cur_guard {
-            g
-        } else {
-            return None;
-        };
+        let guard_pos = self.cur_guard?;
         let (guard_point, guard_dir) = guard_pos;
 
         let facing = point_facing(&guard_pos);
@@ -215,11 +369,11 @@ pub enum Direction {
 
 impl Direction {
     pub fn turn(&self) -> Self {
-        match self {
-            &Self::North => Self::East,
-            &Self::East => Self::South,
-            &Self::South => Self::West,
-            &Self::West => Self::North,
+        match *self {
+            Self::North => Self::East,
+            Self::East => Self::South,
+            Self::South => Self::West,
+            Self::West => Self::North,
         }
     }
 
@@ -233,6 +387,234 @@ impl Direction {
     }
 }
 
+/// Traces of the guard's route, rendered for the terminal or as an image to
+/// see why an obstacle does or doesn't send the guard into a loop
+pub mod route {
+    use std::{
+        collections::HashMap,
+        fmt::{self, Write},
+    };
+
+    use crate::render::{ansi, Image, Rgb};
+
+    use super::{grid::Point, Direction, Map, MapItem, Position};
+
+    const EMPTY: Rgb = [16, 16, 24];
+    const OBSTACLE: Rgb = [110, 110, 120];
+    const PLACED: Rgb = [220, 50, 50];
+    const PATH: Rgb = [60, 120, 220];
+    const CYCLE: Rgb = [250, 200, 40];
+    const START: Rgb = [60, 200, 90];
+
+    /// Every position the guard passes through, with an obstacle optionally
+    /// placed first
+    pub struct Route {
+        map: Map,
+        steps: Vec<Position>,
+        outcome: Outcome,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Outcome {
+        /// The guard walked off the map
+        Exits,
+        /// The guard returned to an earlier position, with the cycle starting
+        /// at this step
+        Loops { cycle_start: usize },
+    }
+
+    impl Route {
+        /// Walk the guard from their starting position until they leave the
+        /// map or loop. An `obstacle` at (`x`, `y`) which can't be placed (off
+        /// the map, on an existing obstacle or the guard's start) is ignored.
+        pub fn trace(mut map: Map, obstacle: Option<(usize, usize)>) -> Self {
+            map.reset();
+            if let Some((x, y)) = obstacle {
+                map.place_obstacle(&Point::new(x as isize, y as isize));
+            }
+
+            let mut seen: HashMap<Position, usize> = HashMap::new();
+            let mut steps = vec![];
+            let mut outcome = Outcome::Exits;
+            while let Some(pos) = map.move_guard() {
+                if let Some(&cycle_start) = seen.get(&pos) {
+                    outcome = Outcome::Loops { cycle_start };
+                    break;
+                }
+                seen.insert(pos, steps.len());
+                steps.push(pos);
+            }
+
+            Self {
+                map,
+                steps,
+                outcome,
+            }
+        }
+
+        pub fn outcome(&self) -> Outcome {
+            self.outcome
+        }
+
+        /// The obstacle which was placed, if any
+        pub fn obstacle(&self) -> Option<(usize, usize)> {
+            self.map.obstacle.map(|p| (p.x() as usize, p.y() as usize))
+        }
+
+        /// The positions making up the loop, if the guard loops
+        pub fn cycle(&self) -> &[Position] {
+            match self.outcome {
+                Outcome::Exits => &[],
+                Outcome::Loops { cycle_start } => &self.steps[cycle_start..],
+            }
+        }
+
+        /// Draw the map with the path as in the puzzle description: `|` and
+        /// `-` where the guard only moved vertically or horizontally, `+`
+        /// where they did both (including turns), and `O` for the placed
+        /// obstacle. With `color`, the loop is highlighted using ANSI codes.
+        pub fn render_text(&self, color: bool) -> String {
+            let cells = self.cells();
+            let mut out = String::new();
+
+            for y in 0..self.map.grid.rows() {
+                for x in 0..self.map.grid.cols() {
+                    let (glyph, cell) = self.glyph(&cells, Point::new(x as isize, y as isize));
+                    if color && cell != Cell::Empty {
+                        write!(out, "{}{}{}", ansi::fg(cell.color()), glyph, ansi::RESET).unwrap();
+                    } else {
+                        out.push(glyph);
+                    }
+                }
+                out.push('\n');
+            }
+
+            out
+        }
+
+        /// Draw the map as an image, one `scale`x`scale` block per tile
+        pub fn render_image(&self, scale: usize) -> Image {
+            let cells = self.cells();
+            let grid = &self.map.grid;
+            let mut image = Image::new(grid.cols() * scale, grid.rows() * scale, EMPTY);
+
+            for y in 0..grid.rows() {
+                for x in 0..grid.cols() {
+                    let (_, cell) = self.glyph(&cells, Point::new(x as isize, y as isize));
+                    image.fill_cell(x, y, scale, cell.color());
+                }
+            }
+
+            image
+        }
+
+        /// Directions of travel through each visited point, and whether it's
+        /// part of the loop
+        fn cells(&self) -> HashMap<Point, (bool, bool, bool)> {
+            let mut cells: HashMap<Point, (bool, bool, bool)> = HashMap::new();
+            let cycle_start = match self.outcome {
+                Outcome::Exits => self.steps.len(),
+                Outcome::Loops { cycle_start } => cycle_start,
+            };
+
+            for (step, (point, dir)) in self.steps.iter().enumerate() {
+                let (vertical, horizontal, in_cycle) = cells.entry(*point).or_default();
+                match dir {
+                    Direction::North | Direction::South => *vertical = true,
+                    Direction::East | Direction::West => *horizontal = true,
+                }
+                *in_cycle |= step >= cycle_start;
+            }
+
+            cells
+        }
+
+        fn glyph(&self, cells: &HashMap<Point, (bool, bool, bool)>, point: Point) -> (char, Cell) {
+            let (start, start_dir) = self.map.guard_origin;
+            if self.map.obstacle == Some(point) {
+                return ('O', Cell::Placed);
+            }
+            if point == start {
+                let glyph = match start_dir {
+                    Direction::North => '^',
+                    Direction::East => '>',
+                    Direction::South => 'v',
+                    Direction::West => '<',
+                };
+                return (glyph, Cell::Start);
+            }
+            if self.map.grid.get(&point) == Some(&MapItem::Obstacle) {
+                return ('#', Cell::Obstacle);
+            }
+
+            match cells.get(&point) {
+                None => ('.', Cell::Empty),
+                Some(&(vertical, horizontal, in_cycle)) => {
+                    let glyph = match (vertical, horizontal) {
+                        (true, false) => '|',
+                        (false, true) => '-',
+                        _ => '+',
+                    };
+                    (glyph, if in_cycle { Cell::Cycle } else { Cell::Path })
+                }
+            }
+        }
+    }
+
+    impl fmt::Display for Route {
+        /// Explain the outcome, e.g. where and how long the loop is
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.obstacle() {
+                Some((x, y)) => write!(f, "with an obstacle at {},{} ", x, y)?,
+                None => write!(f, "without an extra obstacle ")?,
+            }
+
+            match self.outcome {
+                Outcome::Exits => write!(
+                    f,
+                    "the guard leaves the map after {} steps",
+                    self.steps.len()
+                ),
+                Outcome::Loops { cycle_start } => {
+                    let (point, dir) = self.steps[cycle_start];
+                    write!(
+                        f,
+                        "the guard loops after {} steps, repeating a {} step cycle from {},{} facing {:?}",
+                        self.steps.len(),
+                        self.cycle().len(),
+                        point.x(),
+                        point.y(),
+                        dir
+                    )
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Cell {
+        Empty,
+        Obstacle,
+        Placed,
+        Start,
+        Path,
+        Cycle,
+    }
+
+    impl Cell {
+        fn color(self) -> Rgb {
+            match self {
+                Cell::Empty => EMPTY,
+                Cell::Obstacle => OBSTACLE,
+                Cell::Placed => PLACED,
+                Cell::Start => START,
+                Cell::Path => PATH,
+                Cell::Cycle => CYCLE,
+            }
+        }
+    }
+}
+
 mod grid {
     use std::ops::{Add, Mul};
 
@@ -304,10 +686,18 @@ mod grid {
             Self { x, y }
         }
 
+        pub fn x(&self) -> isize {
+            self.x
+        }
+
+        pub fn y(&self) -> isize {
+            self.y
+        }
+
         pub fn on_grid<T>(&self, grid: &Grid<T>) -> bool {
-            self.x > 0
+            self.x >= 0
                 && (self.x as usize) < grid.cols()
-                && self.y > 0
+                && self.y >= 0
                 && (self.y as usize) < grid.rows()
         }
     }
@@ -334,3 +724,136 @@ mod grid {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        day_test,
+        harness::{testing, Day, Params, Part, SolutionInput},
+    };
+
+    use super::{
+        grid::{Grid, Point},
+        route::{Outcome, Route},
+        Map, MapItem, D6,
+    };
+
+    const EXAMPLE: &str = "\
+....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D6, Part::One, EXAMPLE, 41);
+        day_test!(D6, Part::Two, EXAMPLE, 6);
+    }
+
+    #[test]
+    fn test_on_grid() {
+        testing::check(
+            "points are on the grid exactly when they index into it",
+            |rng| {
+                let rows = rng.range(1, 5) as usize;
+                let cols = rng.range(1, 5) as usize;
+                let x = rng.range(0, cols as u64 + 2) as isize - 1;
+                let y = rng.range(0, rows as u64 + 2) as isize - 1;
+                (rows, cols, Point::new(x, y))
+            },
+            |(rows, cols, point)| {
+                let grid = Grid::new(vec![vec![(); *cols]; *rows]);
+                point.on_grid(&grid) == grid.get(point).is_some()
+            },
+        );
+    }
+
+    #[test]
+    fn test_route() {
+        let map = Map::read(EXAMPLE.as_bytes()).unwrap();
+
+        let route = Route::trace(map.clone(), Some((3, 6)));
+        assert_eq!(Outcome::Loops { cycle_start: 0 }, route.outcome());
+        assert_eq!(
+            "\
+....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#...
+",
+            route.render_text(false)
+        );
+
+        let route = Route::trace(map.clone(), Some((7, 7)));
+        assert_eq!(Outcome::Loops { cycle_start: 35 }, route.outcome());
+        assert_eq!(
+            "\
+....#.....
+....+---+#
+....|...|.
+..#.|...|.
+..+-+-+#|.
+..|.|.|.|.
+.#+-^-+-+.
+.+----+O#.
+#+----+...
+......#...
+",
+            route.render_text(false)
+        );
+
+        let route = Route::trace(map, None);
+        assert_eq!(Outcome::Exits, route.outcome());
+        assert!(route.cycle().is_empty());
+    }
+
+    #[test]
+    fn test_loop_placements() {
+        testing::check(
+            "fast part two agrees with tracing every placement",
+            |rng| {
+                // the puzzle guarantees the guard leaves the map without an
+                // extra obstacle
+                loop {
+                    let rows = rng.range(1, 12) as usize;
+                    let cols = rng.range(1, 12) as usize;
+                    let map = testing::guard_map(rng, rows, cols);
+                    let input = Map::read(map.as_bytes()).unwrap();
+                    if Route::trace(input, None).outcome() == Outcome::Exits {
+                        break map;
+                    }
+                }
+            },
+            |map| {
+                let input = Map::read(map.as_bytes()).unwrap();
+                let (rows, cols) = (input.grid.rows(), input.grid.cols());
+                let expected = (0..cols)
+                    .flat_map(|x| (0..rows).map(move |y| (x, y)))
+                    .filter(|(x, y)| {
+                        let point = Point::new(*x as isize, *y as isize);
+                        input.grid.get(&point) == Some(&MapItem::Empty)
+                    })
+                    .filter(|point| {
+                        Route::trace(input.clone(), Some(*point)).outcome() != Outcome::Exits
+                    })
+                    .count();
+
+                D6::run_with(Part::Two, map.as_bytes(), &Params::new())
+                    .is_ok_and(|output| output == expected.to_string())
+            },
+        );
+    }
+}
@@ -1,7 +1,9 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
 
 use crate::{
-    harness::{iter, Day, InputError, Inputs, SolutionInput},
+    harness::{iter, Day, Error, InputError, Inputs, Param, SolutionInput},
     parse,
 };
 
@@ -19,8 +21,8 @@ impl Day for D7 {
 
 pub mod p1 {
     use crate::{
-        day::d7::Equation,
-        harness::{Inputs, Solution},
+        day::d7::{calibration_total, Equation, P1_OPS},
+        harness::{Inputs, Params, Solution},
     };
 
     pub struct P1;
@@ -31,21 +33,19 @@ pub mod p1 {
         type Output = u64;
 
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
-            input.fold_solve(0, |sum, eq| {
-                Ok(if eq.can_solve(false) {
-                    sum + eq.target
-                } else {
-                    sum
-                })
-            })
+            Self::solve_with(input, &Params::new())
+        }
+
+        fn solve_with(input: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+            calibration_total(input, params.get(&P1_OPS)?)
         }
     }
 }
 
 pub mod p2 {
     use crate::{
-        day::d7::Equation,
-        harness::{Inputs, Solution},
+        day::d7::{calibration_total, Equation, P2_OPS},
+        harness::{Inputs, Params, Solution},
     };
 
     pub struct P2;
@@ -56,45 +56,335 @@ pub mod p2 {
         type Output = u64;
 
         fn solve(input: Self::Input) -> crate::harness::Result<Self::Output> {
-            input.fold_solve(0, |sum, eq| {
-                Ok(if eq.can_solve(true) {
-                    sum + eq.target
-                } else {
-                    sum
-                })
-            })
+            Self::solve_with(input, &Params::new())
+        }
+
+        fn solve_with(input: Self::Input, params: &Params) -> crate::harness::Result<Self::Output> {
+            calibration_total(input, params.get(&P2_OPS)?)
         }
     }
 }
 
+static P1_OPS: Param<OpSet> = Param::new("ops", OpSet::of(&[Op::Add, Op::Mul]));
+
+static P2_OPS: Param<OpSet> = Param::new("ops", OpSet::of(&[Op::Add, Op::Mul, Op::Concat]));
+
 pub struct Equation {
     target: u64,
     terms: Vec<u64>,
 }
 
 impl Equation {
-    pub fn can_solve(&self, use_concat: bool) -> bool {
-        let mut stack: Vec<(u64, &[u64])> = vec![(0, &self.terms)];
-
-        while let Some((acc, terms)) = stack.pop() {
-            // backtrack when:
-            // - we've exceeded the target already (no ability to reduce)
-            // - we've hit the end of our search path (used all operators) and did not reach the target
-            if acc > self.target || (acc != self.target && terms.is_empty()) {
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    pub fn can_solve(&self, ops: OpSet) -> bool {
+        self.solve(ops).is_some()
+    }
+
+    /// Find operators from `ops`, applied left to right between the terms,
+    /// which reach the target
+    pub fn solve(&self, ops: OpSet) -> Option<Vec<Op>> {
+        let mut solution = None;
+        self.search(ops, |found, _| {
+            solution = Some(found.to_vec());
+            false
+        });
+        solution
+    }
+
+    /// Count every assignment of operators from `ops` which reaches the
+    /// target, or `None` if there are too many to count
+    pub fn count_solutions(&self, ops: OpSet) -> Option<usize> {
+        let n_ops = ops.iter().count();
+        let mut count = Some(0_usize);
+        self.search(ops, |_, free| {
+            count = n_ops
+                .checked_pow(free as u32)
+                .and_then(|ways| count?.checked_add(ways));
+            count.is_some()
+        });
+        count
+    }
+
+    /// Show the equation with `ops` filled in, e.g. `190 = 10 * 19`
+    pub fn with_ops<'a>(&'a self, ops: &'a [Op]) -> impl fmt::Display + 'a {
+        WithOps { eq: self, ops }
+    }
+
+    /// Depth first search over operator assignments, working backwards from
+    /// the target by undoing the last term with each operator. This prunes
+    /// far harder than searching forwards, as most undos aren't possible
+    /// (the remainder isn't divisible by the term, or doesn't end in its
+    /// digits), and never overflows.
+    ///
+    /// Calls `on_solution` with each assignment reaching the target until it
+    /// returns `false`. Where an operator reaches the remainder from any
+    /// value (e.g. `* 0`), the operators before it can be anything, even if
+    /// they'd overflow or go negative on their own, so the number of leading
+    /// operators left unconstrained is also passed (filled in with the first
+    /// of `ops`).
+    fn search(&self, ops: OpSet, mut on_solution: impl FnMut(&[Op], usize) -> bool) {
+        let Some(first) = self.terms.first() else {
+            if self.target == 0 {
+                on_solution(&[], 0);
+            }
+            return;
+        };
+        // only needed once an op has been undone, so `ops` isn't empty
+        let filler = ops.iter().next().unwrap_or(Op::Add);
+
+        let n_terms = self.terms.len();
+        // each entry is the value the first `len` terms need to evaluate to,
+        // and the op undone to get there
+        let mut stack: Vec<(u64, usize, Option<Op>)> = vec![(self.target, n_terms, None)];
+        // ops undone so far, last first
+        let mut undone: Vec<Op> = Vec::with_capacity(n_terms);
+
+        let mut report = |undone: &[Op], free: usize| {
+            let ops: Vec<Op> = std::iter::repeat_n(filler, free)
+                .chain(undone.iter().rev().copied())
+                .collect();
+            on_solution(&ops, free)
+        };
+
+        while let Some((rem, len, op)) = stack.pop() {
+            undone.truncate(n_terms - len - op.map_or(0, |_| 1));
+            undone.extend(op);
+
+            if len == 1 {
+                if rem == *first && !report(&undone, 0) {
+                    return;
+                }
                 continue;
             }
-            if acc == self.target && terms.is_empty() {
-                return true;
+
+            let term = self.terms[len - 1];
+            for op in ops.iter().rev() {
+                match op.undo(rem, term) {
+                    Undo::None => {}
+                    Undo::One(prev) => stack.push((prev, len - 1, Some(op))),
+                    Undo::Any => {
+                        undone.push(op);
+                        let more = report(&undone, len - 2);
+                        undone.pop();
+                        if !more {
+                            return;
+                        }
+                    }
+                }
             }
-            let next_term = terms.first().unwrap();
-            stack.push((acc + next_term, &terms[1..]));
-            stack.push((acc * next_term, &terms[1..]));
-            if use_concat {
-                stack.push((concat(acc, *next_term), &terms[1..]));
+        }
+    }
+}
+
+/// Total of the targets of the equations which can be solved, failing
+/// rather than wrapping if it doesn't fit in a `u64`
+pub fn calibration_total<'a>(
+    input: Box<dyn Inputs<Equation> + 'a>,
+    ops: OpSet,
+) -> crate::harness::Result<u64> {
+    input.fold_solve(0_u64, |total, eq| {
+        if !eq.can_solve(ops) {
+            return Ok(total);
+        }
+        total.checked_add(eq.target).ok_or_else(|| {
+            Error::SolutionError(Box::new(OverflowError {
+                total,
+                target: eq.target,
+            }))
+        })
+    })
+}
+
+#[derive(Debug, Error)]
+#[error("calibration total overflows a u64 adding {target} to {total}")]
+pub struct OverflowError {
+    total: u64,
+    target: u64,
+}
+
+/// Ways of undoing an operator to find the value before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Undo {
+    /// The result can't be reached with this operator and term
+    None,
+    /// The result is reached from exactly this value
+    One(u64),
+    /// The result is reached from any value
+    Any,
+}
+
+/// Operator placed between the terms of an equation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Mul,
+    Concat,
+    Sub,
+    Xor,
+    Pow,
+}
+
+impl Op {
+    pub const ALL: [Op; 6] = [Op::Add, Op::Mul, Op::Concat, Op::Sub, Op::Xor, Op::Pow];
+
+    /// Apply the operator, or `None` if the result overflows (or is negative)
+    pub fn apply(self, acc: u64, term: u64) -> Option<u64> {
+        match self {
+            Op::Add => acc.checked_add(term),
+            Op::Mul => acc.checked_mul(term),
+            Op::Concat => concat(acc, term),
+            Op::Sub => acc.checked_sub(term),
+            Op::Xor => Some(acc ^ term),
+            Op::Pow => match (acc, term) {
+                (_, 0) => Some(1),
+                (0 | 1, _) => Some(acc),
+                _ => acc.checked_pow(u32::try_from(term).ok()?),
+            },
+        }
+    }
+
+    /// Find the value `acc` for which `acc op term == result`
+    pub fn undo(self, result: u64, term: u64) -> Undo {
+        match self {
+            Op::Add => result.checked_sub(term).map_or(Undo::None, Undo::One),
+            Op::Mul => match (result, term) {
+                (0, 0) => Undo::Any,
+                (_, 0) => Undo::None,
+                _ if result.is_multiple_of(term) => Undo::One(result / term),
+                _ => Undo::None,
+            },
+            Op::Concat => {
+                let Some(shift) = 10_u64.checked_pow(digits(term)) else {
+                    // only `0 || term` fits when the term is this long
+                    return if result == term {
+                        Undo::One(0)
+                    } else {
+                        Undo::None
+                    };
+                };
+                if result % shift == term {
+                    Undo::One(result / shift)
+                } else {
+                    Undo::None
+                }
+            }
+            Op::Sub => result.checked_add(term).map_or(Undo::None, Undo::One),
+            Op::Xor => Undo::One(result ^ term),
+            Op::Pow => match (result, term) {
+                (1, 0) => Undo::Any,
+                (_, 0) => Undo::None,
+                _ => root(result, term).map_or(Undo::None, Undo::One),
+            },
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Mul => "*",
+            Op::Concat => "||",
+            Op::Sub => "-",
+            Op::Xor => "^",
+            Op::Pow => "**",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl FromStr for Op {
+    type Err = UnknownOpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Op::ALL
+            .into_iter()
+            .find(|op| op.symbol() == s.trim())
+            .ok_or_else(|| UnknownOpError(s.to_string()))
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("unknown operator '{0}', expected one of + * || - ^ **")]
+pub struct UnknownOpError(String);
+
+/// Set of operators a solver may place between terms, e.g. parsed from
+/// `+,*,||`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpSet(u8);
+
+impl OpSet {
+    pub const fn of(ops: &[Op]) -> Self {
+        let mut bits = 0;
+        let mut idx = 0;
+        while idx < ops.len() {
+            bits |= 1 << ops[idx] as u8;
+            idx += 1;
+        }
+        Self(bits)
+    }
+
+    pub fn contains(self, op: Op) -> bool {
+        self.0 & op.bit() != 0
+    }
+
+    /// Ops in the set, in [`Op::ALL`] order
+    pub fn iter(self) -> impl DoubleEndedIterator<Item = Op> {
+        Op::ALL.into_iter().filter(move |op| self.contains(*op))
+    }
+}
+
+impl FromStr for OpSet {
+    type Err = UnknownOpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ops: Vec<Op> = s.split(',').map(Op::from_str).collect::<Result<_, _>>()?;
+        Ok(Self::of(&ops))
+    }
+}
+
+impl fmt::Display for OpSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ops: Vec<&str> = self.iter().map(Op::symbol).collect();
+        f.write_str(&ops.join(","))
+    }
+}
+
+struct WithOps<'a> {
+    eq: &'a Equation,
+    ops: &'a [Op],
+}
+
+impl fmt::Display for WithOps<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} =", self.eq.target)?;
+        for (idx, term) in self.eq.terms.iter().enumerate() {
+            if let Some(op) = idx.checked_sub(1).and_then(|idx| self.ops.get(idx)) {
+                write!(f, " {}", op)?;
             }
+            write!(f, " {}", term)?;
         }
+        Ok(())
+    }
+}
 
-        false
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.target)?;
+        for term in &self.terms {
+            write!(f, " {}", term)?;
+        }
+        Ok(())
     }
 }
 
@@ -126,13 +416,247 @@ impl FromStr for Equation {
 
 impl<'a> SolutionInput<'a> for Box<dyn Inputs<Equation> + 'a> {
     fn read(reader: impl std::io::BufRead + 'a) -> crate::harness::input::Result<Self> {
-        Ok(Box::new(
-            iter::lines(reader).map(|line| Equation::from_str(&line?)),
-        ))
+        Ok(Box::new(iter::lines(reader).enumerate_lines().try_map(
+            |(line_num, line)| Equation::from_str(&line).map_err(|err| err.at_line(line_num)),
+        )))
     }
 }
 
-pub fn concat(x: u64, y: u64) -> u64 {
-    let y_digits = y.ilog10() + 1;
-    x * 10_u64.pow(y_digits) + y
+/// Concatenate the decimal digits of `x` and `y`, or `None` if the result
+/// overflows
+pub fn concat(x: u64, y: u64) -> Option<u64> {
+    x.checked_mul(10_u64.checked_pow(digits(y))?)?
+        .checked_add(y)
+}
+
+/// Integer `exp`th root of `x`, if there is one
+fn root(x: u64, exp: u64) -> Option<u64> {
+    if x <= 1 || exp == 1 {
+        return Some(x);
+    }
+    // any base from 2 up overflows for larger exponents
+    let exp = u32::try_from(exp).ok().filter(|exp| *exp < u64::BITS)?;
+    let estimate = (x as f64).powf(1.0 / exp as f64).round() as u64;
+    (estimate.saturating_sub(1)..=estimate + 1).find(|base| base.checked_pow(exp) == Some(x))
+}
+
+fn digits(x: u64) -> u32 {
+    x.checked_ilog10().map_or(1, |log| log + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        day_test,
+        harness::{testing, Day, Params, Part},
+    };
+
+    use super::{Equation, Op, OpSet, Undo, D7, P1_OPS, P2_OPS};
+
+    const EXAMPLE: &str = "\
+190: 10 19
+3267: 81 40 27
+83: 17 5
+156: 15 6
+7290: 6 8 6 15
+161011: 16 10 13
+192: 17 8 14
+21037: 9 7 18 13
+292: 11 6 16 20
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D7, Part::One, EXAMPLE, 3749);
+        day_test!(D7, Part::Two, EXAMPLE, 11387);
+    }
+
+    #[test]
+    fn test_concat_only_adds_solutions() {
+        testing::check(
+            "equations solvable without concat are solvable with it",
+            |rng| testing::equations(rng, 10, 6, true),
+            |input| {
+                input.lines().all(|line| {
+                    let eq = Equation::from_str(line).unwrap();
+                    !eq.can_solve(P1_OPS.default) || eq.can_solve(P2_OPS.default)
+                })
+            },
+        );
+    }
+
+    #[test]
+    fn test_solve() {
+        let eq = Equation::from_str("3267: 81 40 27").unwrap();
+
+        // either 81 + 40 * 27 or 81 * 40 + 27
+        let ops = eq.solve(P1_OPS.default).unwrap();
+        assert!(ops == [Op::Add, Op::Mul] || ops == [Op::Mul, Op::Add]);
+        assert_eq!(Some(2), eq.count_solutions(P1_OPS.default));
+        assert_eq!(
+            "3267 = 81 + 40 * 27",
+            eq.with_ops(&[Op::Add, Op::Mul]).to_string()
+        );
+
+        let eq = Equation::from_str("7290: 6 8 6 15").unwrap();
+        assert_eq!(None, eq.solve(P1_OPS.default));
+        assert_eq!(
+            Some(vec![Op::Mul, Op::Concat, Op::Mul]),
+            eq.solve(P2_OPS.default)
+        );
+        assert_eq!(
+            "7290 = 6 * 8 || 6 * 15",
+            eq.with_ops(&eq.solve(P2_OPS.default).unwrap()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_solutions_reach_target() {
+        testing::check(
+            "reported operators evaluate to the target",
+            |rng| testing::equations(rng, 10, 6, true),
+            |input| {
+                input.lines().all(|line| {
+                    let eq = Equation::from_str(line).unwrap();
+                    match eq.solve(P2_OPS.default) {
+                        Some(ops) => {
+                            let value = eq.terms[1..]
+                                .iter()
+                                .zip(&ops)
+                                .try_fold(eq.terms[0], |acc, (term, op)| op.apply(acc, *term));
+                            value == Some(eq.target) && eq.count_solutions(P2_OPS.default) > Some(0)
+                        }
+                        None => eq.count_solutions(P2_OPS.default) == Some(0),
+                    }
+                })
+            },
+        );
+    }
+
+    #[test]
+    fn test_undo() {
+        assert_eq!(Undo::One(156), Op::Concat.undo(156_486, 486));
+        assert_eq!(Undo::None, Op::Concat.undo(156_486, 86_486));
+        assert_eq!(Undo::One(15), Op::Concat.undo(150, 0));
+        assert_eq!(Undo::One(0), Op::Concat.undo(u64::MAX, u64::MAX));
+        assert_eq!(Undo::None, Op::Mul.undo(10, 3));
+        assert_eq!(Undo::None, Op::Mul.undo(10, 0));
+        assert_eq!(Undo::Any, Op::Mul.undo(0, 0));
+        assert_eq!(Undo::None, Op::Add.undo(3, 4));
+    }
+
+    #[test]
+    fn test_zero_terms() {
+        let eq = Equation::from_str("0: 5 7 0").unwrap();
+        assert_eq!(Some(vec![Op::Add, Op::Mul]), eq.solve(P1_OPS.default));
+        // anything * 0, or 5 * 7 + 0 doesn't reach 0
+        assert_eq!(Some(2), eq.count_solutions(P1_OPS.default));
+
+        let eq = Equation::from_str("50: 5 0").unwrap();
+        assert_eq!(Some(vec![Op::Concat]), eq.solve(P2_OPS.default));
+    }
+
+    #[test]
+    fn test_many_terms_after_zero() {
+        // every one of the 3^60 ways to combine the terms before the * 0 works,
+        // which is too many to count, or to try one at a time
+        let terms: Vec<String> = (1..=61).map(|n| n.to_string()).collect();
+        let eq = Equation::from_str(&format!("0: {} 0", terms.join(" "))).unwrap();
+        assert_eq!(None, eq.count_solutions(P2_OPS.default));
+        assert_eq!(
+            Some([vec![Op::Add; 60], vec![Op::Mul]].concat()),
+            eq.solve(P2_OPS.default)
+        );
+
+        let terms: Vec<String> = (1..=30).map(|n| n.to_string()).collect();
+        let eq = Equation::from_str(&format!("0: {} 0", terms.join(" "))).unwrap();
+        assert_eq!(Some(3_usize.pow(29)), eq.count_solutions(P2_OPS.default));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = format!("{0}: {0}\n{0}: {0}\n", u64::MAX);
+        let err = D7::run_with(Part::One, input.as_bytes(), &Params::new()).unwrap_err();
+        assert!(err.to_string().contains("overflows"));
+
+        // products which would overflow are never reached searching backwards
+        let eq = Equation::from_str(&format!("{}: {} {}", u64::MAX, u64::MAX, u64::MAX)).unwrap();
+        assert!(!eq.can_solve(P2_OPS.default));
+    }
+
+    #[test]
+    fn test_matches_forward_search() {
+        testing::check(
+            "backward search counts the same solutions as trying every assignment",
+            |rng| {
+                let ops: Vec<Op> = Op::ALL.into_iter().filter(|_| rng.chance(1, 2)).collect();
+                (OpSet::of(&ops), testing::equations(rng, 10, 5, true))
+            },
+            |(ops, input)| {
+                let ops: Vec<Op> = ops.iter().collect();
+                input.lines().all(|line| {
+                    let eq = Equation::from_str(line).unwrap();
+                    let n_gaps = eq.terms.len() as u32 - 1;
+                    let brute_force = (0..ops.len().pow(n_gaps))
+                        .filter(|assignment| {
+                            let mut value = Some(eq.terms[0]);
+                            for (idx, term) in eq.terms[1..].iter().enumerate() {
+                                let op = ops[assignment / ops.len().pow(idx as u32) % ops.len()];
+                                // `* 0` and `** 0` reach the same value from
+                                // anything, even after overflowing
+                                value = match (value, op, term) {
+                                    (_, Op::Mul, 0) => Some(0),
+                                    (_, Op::Pow, 0) => Some(1),
+                                    (Some(acc), _, _) => op.apply(acc, *term),
+                                    (None, _, _) => None,
+                                };
+                            }
+                            value == Some(eq.target)
+                        })
+                        .count();
+                    eq.count_solutions(OpSet::of(&ops)) == Some(brute_force)
+                })
+            },
+        );
+    }
+
+    #[test]
+    fn test_other_ops() {
+        let ops: OpSet = "-,^,**".parse().unwrap();
+        assert_eq!("-,^,**", ops.to_string());
+        assert!("+,%".parse::<OpSet>().is_err());
+
+        let eq = Equation::from_str("9: 5 2 3").unwrap();
+        assert_eq!(None, eq.solve(ops));
+        let eq = Equation::from_str("9: 5 2 2").unwrap();
+        assert_eq!(Some(vec![Op::Sub, Op::Pow]), eq.solve(ops));
+        let eq = Equation::from_str("6: 5 3").unwrap();
+        assert_eq!(Some(vec![Op::Xor]), eq.solve(ops));
+
+        // anything ** 0 is 1, even going negative (2 - 3), so 9 ways, plus
+        // 2 ^ 3 ** 4 = 1 followed by - 0 or ^ 0
+        let eq = Equation::from_str("1: 2 3 4 0").unwrap();
+        assert_eq!(Some(11), eq.count_solutions(ops));
+
+        assert_eq!(Undo::One(3), Op::Pow.undo(81, 4));
+        assert_eq!(Undo::None, Op::Pow.undo(80, 4));
+        assert_eq!(Undo::One(2), Op::Pow.undo(1 << 63, 63));
+        assert_eq!(Undo::None, Op::Pow.undo(u64::MAX, 64));
+        assert_eq!(Undo::None, Op::Sub.undo(u64::MAX, 1));
+    }
+
+    #[test]
+    fn test_ops_param() {
+        let params: Params = [("ops".to_string(), "+,*,-".to_string())]
+            .into_iter()
+            .collect();
+        // 83 = 17 * 5 - 2 and 12 = 17 - 5
+        let input = "83: 17 5 2\n12: 17 5\n";
+        assert_eq!(
+            "95",
+            D7::run_with(Part::One, input.as_bytes(), &params).unwrap()
+        );
+    }
 }
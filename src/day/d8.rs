@@ -26,8 +26,8 @@ impl<'a> Solution<'a> for P1 {
         for (_, locs) in map.antennae_by_freq {
             for (i, (a1_col, a1_row)) in locs.iter().enumerate() {
                 let (a1_col, a1_row) = (*a1_col as isize, *a1_row as isize);
-                for j in i + 1..locs.len() {
-                    let (a2_col, a2_row) = (locs[j].0 as isize, locs[j].1 as isize);
+                for (a2_col, a2_row) in locs.iter().skip(i + 1) {
+                    let (a2_col, a2_row) = (*a2_col as isize, *a2_row as isize);
                     let d_col = a2_col - a1_col;
                     let d_row = a2_row - a1_row;
                     let antinode_1 = (a2_col + d_col, a2_row + d_row);
@@ -61,8 +61,8 @@ impl<'a> Solution<'a> for P2 {
         for (_, locs) in map.antennae_by_freq {
             for (i, (a1_col, a1_row)) in locs.iter().enumerate() {
                 let (a1_col, a1_row) = (*a1_col as isize, *a1_row as isize);
-                for j in i + 1..locs.len() {
-                    let (a2_col, a2_row) = (locs[j].0 as isize, locs[j].1 as isize);
+                for (a2_col, a2_row) in locs.iter().skip(i + 1) {
+                    let (a2_col, a2_row) = (*a2_col as isize, *a2_row as isize);
                     let d_col = a2_col - a1_col;
                     let d_row = a2_row - a1_row;
 
@@ -118,3 +118,31 @@ impl<'a> SolutionInput<'a> for Map {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{day_test, harness::Part};
+
+    use super::D8;
+
+    const EXAMPLE: &str = "\
+............
+........0...
+.....0......
+.......0....
+....0.......
+......A.....
+............
+............
+........A...
+.........A..
+............
+............
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D8, Part::One, EXAMPLE, 14);
+        day_test!(D8, Part::Two, EXAMPLE, 34);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::harness::Day;
+use std::iter;
+
+use crate::harness::{input, Day, InputError, SolutionInput};
 
 pub struct D9;
 
@@ -13,9 +15,9 @@ impl Day for D9 {
 }
 
 pub mod p1 {
-    use std::iter;
+    use crate::harness::Solution;
 
-    use crate::harness::{input, InputError, Solution, SolutionInput};
+    use super::{compute_checksum, Disk, Move};
 
     pub struct P1;
 
@@ -25,12 +27,21 @@ pub mod p1 {
         type Output = usize;
 
         fn solve(mut disk: Self::Input) -> crate::harness::Result<Self::Output> {
-            fragment_disk(&mut disk);
+            fragment_disk(&mut disk, |_, _| {});
             Ok(compute_checksum(&disk))
         }
     }
 
-    fn fragment_disk(disk: &mut [Option<usize>]) {
+    /// Move blocks one at a time from the end of the disk to the leftmost
+    /// free block, calling `on_move` after each
+    pub(super) fn fragment_disk(
+        disk: &mut [Option<usize>],
+        mut on_move: impl FnMut(Move, &[Option<usize>]),
+    ) {
+        if disk.is_empty() {
+            return;
+        }
+
         let mut l = 0;
         let mut r = disk.len() - 1;
 
@@ -43,52 +54,29 @@ pub mod p1 {
             }
             if l < r {
                 disk[l] = disk[r].take();
+                let file_id = disk[l].unwrap();
+                on_move(
+                    Move {
+                        file_id,
+                        from: r,
+                        to: l,
+                        len: 1,
+                    },
+                    disk,
+                );
             } else {
                 break;
             }
         }
     }
-
-    fn compute_checksum(disk: &Disk) -> usize {
-        disk.iter()
-            .enumerate()
-            .map(|(i, block)| match block {
-                Some(n) => i * (*n as usize),
-                None => 0,
-            })
-            .sum()
-    }
-
-    type Disk = Vec<Option<usize>>;
-
-    impl<'a> SolutionInput<'a> for Disk {
-        fn read(mut reader: impl std::io::BufRead + 'a) -> input::Result<Self> {
-            let mut input = String::new();
-            reader.read_to_string(&mut input)?;
-            let mut disk = vec![];
-
-            for (i, c) in input.trim().char_indices() {
-                let num_blocks = c.to_digit(10).ok_or(InputError::InvalidInput {
-                    msg: format!("Non-digit char in input: '{}'", c),
-                    source: None,
-                })? as usize;
-
-                let block = if i % 2 == 0 { Some(i / 2) } else { None };
-
-                for b in iter::repeat_n(block, num_blocks) {
-                    disk.push(b);
-                }
-            }
-
-            Ok(disk)
-        }
-    }
 }
 
 pub mod p2 {
-    use std::iter;
+    use std::{cmp::Reverse, collections::BinaryHeap};
+
+    use crate::harness::Solution;
 
-    use crate::harness::{input, InputError, Solution, SolutionInput};
+    use super::{compute_checksum, runs, Disk, Move};
 
     pub struct P2;
 
@@ -98,116 +86,399 @@ pub mod p2 {
         type Output = usize;
 
         fn solve(mut disk: Self::Input) -> crate::harness::Result<Self::Output> {
-            move_files(&mut disk);
+            move_files(&mut disk, |_, _| {});
             Ok(compute_checksum(&disk))
         }
     }
 
-    fn move_files(disk: &mut Disk) {
-        let mut file = disk.len() as isize - 1;
-
-        loop {
-            // find the next file to move
-            while file >= 0 && disk[file as usize].file_id.is_none() {
-                file -= 1;
-            }
-            if file < 0 {
-                // no remaining files, we're done
-                break;
-            }
-
-            // try to find a large-enough free block
-            let mut free: Option<isize> = None;
-            for i in 0..file {
-                if disk[i as usize].file_id.is_none()
-                    && disk[i as usize].size >= disk[file as usize].size
-                {
-                    free.replace(i);
-                    break;
+    /// Move each file, highest ID first, to the leftmost span of free blocks
+    /// which fits it, if that's left of the file.
+    ///
+    /// The free spans of each size are kept in a min-heap of start positions,
+    /// so the leftmost fitting span is the smallest start among the heaps for
+    /// sizes at least the file's. What's left of a span after a file moves in
+    /// goes back in the heap for its new size. Space freed by a file is never
+    /// reused, as it's right of every file still to move.
+    ///
+    /// Calls `on_move` after each file moves.
+    pub(super) fn move_files(
+        disk: &mut [Option<usize>],
+        mut on_move: impl FnMut(Move, &[Option<usize>]),
+    ) {
+        let mut files = vec![];
+        let mut free: Vec<BinaryHeap<Reverse<usize>>> = vec![];
+        for (file_id, start, len) in runs(disk) {
+            match file_id {
+                Some(id) => files.push((id, start, len)),
+                None => {
+                    if free.len() <= len {
+                        free.resize_with(len + 1, BinaryHeap::new);
+                    }
+                    free[len].push(Reverse(start));
                 }
             }
-            if free.is_none() {
-                // no free block for this file, move to the next file
-                file -= 1;
+        }
+        files.sort_unstable_by_key(|(id, _, _)| Reverse(*id));
+
+        for (id, start, len) in files {
+            let Some(size) = (len..free.len())
+                .filter_map(|size| free[size].peek().map(|Reverse(pos)| (*pos, size)))
+                .filter(|(pos, _)| *pos < start)
+                .min()
+                .map(|(_, size)| size)
+            else {
                 continue;
-            }
+            };
 
-            let l = free.unwrap() as usize;
-            let r = file as usize;
-            let file_size = disk[r].size;
-            let free_size = disk[l].size;
-            assert!(l < r && free_size >= file_size);
-
-            disk[l] = disk[r];
-            disk[r].file_id.take();
-            if free_size > file_size {
-                disk.insert(
-                    l + 1,
-                    Block {
-                        file_id: None,
-                        size: free_size - file_size,
-                    },
-                );
-                file += 1;
+            let Reverse(pos) = free[size].pop().unwrap();
+            disk[pos..pos + len].fill(Some(id));
+            disk[start..start + len].fill(None);
+            if size > len {
+                free[size - len].push(Reverse(pos + len));
             }
+            on_move(
+                Move {
+                    file_id: id,
+                    from: start,
+                    to: pos,
+                    len,
+                },
+                disk,
+            );
         }
     }
+}
 
-    fn compute_checksum(disk: &Disk) -> usize {
+/// Blocks of a file moved during compaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Move {
+    pub file_id: usize,
+    pub from: usize,
+    pub to: usize,
+    pub len: usize,
+}
+
+/// Step by step view of compaction, to check what each part does with a
+/// disk map
+pub mod trace {
+    use std::{collections::HashSet, fmt};
+
+    use crate::harness::Part;
+
+    use super::{p1, p2, runs, Move};
+
+    /// Draw the disk in the puzzle's notation, e.g. `00...111...2...`. File
+    /// IDs from 10 continue with `a`-`z`, and any beyond that are `#`.
+    pub fn render(disk: &[Option<usize>]) -> String {
         disk.iter()
-            .flat_map(|block| block.contents())
-            .enumerate()
-            .map(|(i, data)| match data {
-                Some(file_id) => i * file_id,
-                None => 0,
+            .map(|block| match block {
+                None => '.',
+                Some(id) => u32::try_from(*id)
+                    .ok()
+                    .and_then(|id| char::from_digit(id, 36))
+                    .unwrap_or('#'),
             })
-            .sum()
+            .collect()
     }
 
-    #[derive(Debug, Clone, Copy)]
-    pub struct Block {
-        file_id: Option<usize>,
-        size: usize,
+    /// Compact the disk as in `part`, calling `on_move` after each move
+    pub fn compact(
+        disk: &mut [Option<usize>],
+        part: Part,
+        mut on_move: impl FnMut(Move, &[Option<usize>]),
+    ) -> Summary {
+        let files: HashSet<usize> = disk.iter().flatten().copied().collect();
+        let mut moved = HashSet::new();
+        let mut moves = 0;
+        let mut on_move = |mv: Move, disk: &[Option<usize>]| {
+            moves += 1;
+            moved.insert(mv.file_id);
+            on_move(mv, disk);
+        };
+
+        match part {
+            Part::One => p1::fragment_disk(disk, &mut on_move),
+            Part::Two => p2::move_files(disk, &mut on_move),
+        }
+
+        Summary {
+            moves,
+            files: files.len(),
+            stuck: stuck(disk, &moved),
+            fragmentation: fragmentation(disk),
+        }
     }
 
-    impl Block {
-        pub fn contents(&self) -> Vec<Option<usize>> {
-            iter::repeat_n(self.file_id, self.size).collect()
+    /// Files which weren't moved even though there's free space to their
+    /// left, i.e. no free span there was big enough. Moves only shuffle free
+    /// space around to the left of files which haven't moved, so the free
+    /// space left of a file after compaction is what it had to move into.
+    fn stuck(disk: &[Option<usize>], moved: &HashSet<usize>) -> usize {
+        let mut free_before = false;
+        let mut stuck = HashSet::new();
+        for (file_id, _, _) in runs(disk) {
+            match file_id {
+                None => free_before = true,
+                Some(id) if free_before && !moved.contains(&id) => {
+                    stuck.insert(id);
+                }
+                Some(_) => {}
+            }
         }
+        stuck.len()
     }
 
-    type Disk = Vec<Block>;
+    /// Fraction of free blocks stranded before the last file block, rather
+    /// than gathered at the end of the disk. 0 when fully compacted.
+    pub fn fragmentation(disk: &[Option<usize>]) -> f64 {
+        let free = disk.iter().filter(|block| block.is_none()).count();
+        let used_end = disk.iter().rposition(Option::is_some).map_or(0, |i| i + 1);
+        let stranded = runs(&disk[..used_end])
+            .filter(|(file_id, _, _)| file_id.is_none())
+            .map(|(_, _, len)| len)
+            .sum::<usize>();
+
+        if free == 0 {
+            0.0
+        } else {
+            stranded as f64 / free as f64
+        }
+    }
 
-    impl<'a> SolutionInput<'a> for Disk {
-        fn read(mut reader: impl std::io::BufRead + 'a) -> input::Result<Self> {
-            let mut input = String::new();
-            reader.read_to_string(&mut input)?;
-            let mut disk = vec![];
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Summary {
+        /// Moves made, blocks for part one and files for part two
+        pub moves: usize,
+        /// Files on the disk (with at least one block)
+        pub files: usize,
+        /// Files which couldn't move, as no free span to their left was big
+        /// enough. Files with no free space to their left don't count.
+        pub stuck: usize,
+        /// See [`fragmentation`]
+        pub fragmentation: f64,
+    }
 
-            for (i, c) in input.trim().char_indices() {
-                let size = c.to_digit(10).ok_or(InputError::InvalidInput {
-                    msg: format!("Non-digit char in input: '{}'", c),
-                    source: None,
-                })? as usize;
+    impl fmt::Display for Summary {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "{} moves, {} of {} files couldn't move, {:.1}% of free space fragmented",
+                self.moves,
+                self.stuck,
+                self.files,
+                self.fragmentation * 100.0
+            )
+        }
+    }
+}
 
-                if size == 0 {
-                    continue;
-                }
+/// Block by block layout of the disk, with the ID of the file in each block
+pub type Disk = Vec<Option<usize>>;
 
-                disk.push(if i % 2 == 0 {
-                    Block {
-                        file_id: Some(i / 2),
-                        size,
-                    }
-                } else {
-                    Block {
-                        file_id: None,
-                        size,
-                    }
-                });
+impl<'a> SolutionInput<'a> for Disk {
+    fn read(mut reader: impl std::io::BufRead + 'a) -> input::Result<Self> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+        let mut disk = vec![];
+
+        for (i, c) in input.trim().char_indices() {
+            let num_blocks = c.to_digit(10).ok_or(InputError::InvalidInput {
+                msg: format!("Non-digit char in input: '{}'", c),
+                source: None,
+            })? as usize;
+
+            let block = if i % 2 == 0 { Some(i / 2) } else { None };
+
+            disk.extend(iter::repeat_n(block, num_blocks));
+        }
+
+        Ok(disk)
+    }
+}
+
+/// Maximal runs of blocks with the same contents, as `(file_id, start, len)`
+fn runs(disk: &[Option<usize>]) -> impl Iterator<Item = (Option<usize>, usize, usize)> + '_ {
+    disk.chunk_by(|a, b| a == b).scan(0, |start, run| {
+        let span = (run[0], *start, run.len());
+        *start += run.len();
+        Some(span)
+    })
+}
+
+fn compute_checksum(disk: &[Option<usize>]) -> usize {
+    disk.iter()
+        .enumerate()
+        .map(|(i, block)| match block {
+            Some(n) => i * *n,
+            None => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        day_test,
+        harness::{testing, Day, Params, Part, SolutionInput},
+    };
+
+    use super::{p2, trace, Disk, D9};
+
+    const EXAMPLE: &str = "\
+2333133121414131402
+";
+
+    #[test]
+    fn test_example() {
+        day_test!(D9, Part::One, EXAMPLE, 1928);
+        day_test!(D9, Part::Two, EXAMPLE, 2858);
+    }
+
+    /// Checksum of the disk before any blocks are moved
+    fn uncompacted_checksum(disk_map: &str) -> usize {
+        let mut pos = 0;
+        let mut checksum = 0;
+        for (i, c) in disk_map.trim().char_indices() {
+            let size = c.to_digit(10).unwrap() as usize;
+            if i % 2 == 0 {
+                checksum += (pos..pos + size).map(|p| p * (i / 2)).sum::<usize>();
             }
+            pos += size;
+        }
+        checksum
+    }
 
-            Ok(disk)
+    #[test]
+    fn test_compaction_never_increases_checksum() {
+        testing::check(
+            "compacted checksum doesn't exceed the uncompacted checksum",
+            |rng| {
+                let len = rng.range(1, 40) as usize;
+                testing::disk_map(rng, len)
+            },
+            |disk_map| {
+                let naive = uncompacted_checksum(disk_map);
+                [Part::One, Part::Two].into_iter().all(|part| {
+                    let checksum: usize = D9::run_with(part, disk_map.as_bytes(), &Params::new())
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    checksum <= naive
+                })
+            },
+        );
+    }
+
+    /// Part two as described: for each file, scan from the start of the
+    /// disk for the first free span which fits it
+    fn move_files_by_scanning(disk: &mut [Option<usize>]) {
+        let max_id = disk.iter().flatten().max().copied();
+        for id in (0..=max_id.unwrap_or(0)).rev() {
+            let Some(start) = disk.iter().position(|b| *b == Some(id)) else {
+                continue;
+            };
+            let len = disk[start..].iter().take_while(|b| **b == Some(id)).count();
+
+            let mut free_start = 0;
+            while free_start < start {
+                let free_len = disk[free_start..start]
+                    .iter()
+                    .take_while(|b| b.is_none())
+                    .count();
+                if free_len >= len {
+                    disk[free_start..free_start + len].fill(Some(id));
+                    disk[start..start + len].fill(None);
+                    break;
+                }
+                free_start += free_len.max(1);
+            }
         }
     }
+
+    #[test]
+    fn test_move_files_matches_scanning() {
+        testing::check(
+            "moving files via free span heaps matches scanning for free spans",
+            |rng| {
+                let len = rng.range(1, 60) as usize;
+                testing::disk_map(rng, len)
+            },
+            |disk_map| {
+                let disk = Disk::read(disk_map.as_bytes()).unwrap();
+                let mut fast = disk.clone();
+                let mut scanned = disk;
+                p2::move_files(&mut fast, |_, _| {});
+                move_files_by_scanning(&mut scanned);
+                fast == scanned
+            },
+        );
+    }
+
+    fn trace(disk_map: &str, part: Part) -> (Vec<String>, trace::Summary) {
+        let mut disk = Disk::read(disk_map.as_bytes()).unwrap();
+        let mut steps = vec![trace::render(&disk)];
+        let summary = trace::compact(&mut disk, part, |_, disk| {
+            steps.push(trace::render(disk));
+        });
+        (steps, summary)
+    }
+
+    #[test]
+    fn test_trace() {
+        let (steps, summary) = trace(EXAMPLE, Part::Two);
+        assert_eq!(
+            vec![
+                "00...111...2...333.44.5555.6666.777.888899",
+                "0099.111...2...333.44.5555.6666.777.8888..",
+                "0099.1117772...333.44.5555.6666.....8888..",
+                "0099.111777244.333....5555.6666.....8888..",
+                "00992111777.44.333....5555.6666.....8888..",
+            ],
+            steps
+        );
+        assert_eq!(4, summary.moves);
+        // 3, 5, 6 and 8 don't fit anywhere to their left, while 0 and 1
+        // never had free space to their left
+        assert_eq!(10, summary.files);
+        assert_eq!(4, summary.stuck);
+        assert_eq!(12.0 / 14.0, summary.fragmentation);
+
+        let (steps, summary) = trace("12345", Part::One);
+        assert_eq!(
+            vec![
+                "0..111....22222",
+                "02.111....2222.",
+                "022111....222..",
+                "0221112...22...",
+                "02211122..2....",
+                "022111222......",
+            ],
+            steps
+        );
+        assert_eq!(5, summary.moves);
+        assert_eq!(0, summary.stuck);
+        assert_eq!(0.0, summary.fragmentation);
+    }
+
+    #[test]
+    fn test_trace_stuck() {
+        // 4 moves into the first gap, 2 and 3 are too big for any gap to
+        // their left, and 1 is packed against 0 so never could move
+        let (steps, summary) = trace("103231401", Part::Two);
+        assert_eq!(vec!["0111..222.33334", "01114.222.3333."], steps);
+        assert_eq!(1, summary.moves);
+        assert_eq!(5, summary.files);
+        assert_eq!(2, summary.stuck);
+    }
+
+    #[test]
+    fn test_trace_zero_lengths() {
+        // file 1 is empty, so the free spans either side of it are one span
+        // which file 2 moves to the start of, and the empty free span after
+        // file 2 is nothing at all
+        let (steps, summary) = trace("120330", Part::Two);
+        assert_eq!(vec!["0.....222", "0222....."], steps);
+        assert_eq!(2, summary.files);
+        assert_eq!(0, summary.stuck);
+    }
 }
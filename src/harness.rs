@@ -1,11 +1,16 @@
 mod day;
+mod example;
 pub mod input;
 pub mod iter;
+pub mod params;
 mod part;
 mod solution;
+#[cfg(test)]
+pub mod testing;
 
 pub use day::Day;
 pub use input::{InputError, SolutionInput};
 pub use iter::Inputs;
+pub use params::{Param, Params};
 pub use part::*;
 pub use solution::*;
@@ -1,7 +1,12 @@
-use std::{env::current_dir, fs::File, io::BufReader};
+use std::{
+    env::current_dir,
+    fs::File,
+    io::{BufRead, BufReader},
+};
 
 use super::{
-    input::InputError,
+    input::{self, InputError},
+    params::Params,
     part::Part,
     solution::{Result, Solution},
 };
@@ -15,8 +20,14 @@ pub trait Day {
     fn day() -> u8;
 
     /// Run the solution for the given daily `part`
-    fn run(part: Part) -> Result<String> {
-        let mut input_path = current_dir().map_err(|err| InputError::from(err))?;
+    fn run(part: Part, params: &Params) -> Result<String> {
+        Self::run_with(part, Self::input(part)?, params)
+    }
+
+    /// Open the input for the given daily `part`: `input/dNpM` if the part
+    /// has its own input, otherwise `input/dN`
+    fn input(part: Part) -> input::Result<BufReader<File>> {
+        let mut input_path = current_dir()?;
         input_path.push("input");
         input_path.push(format!("d{}p{}", Self::day(), part.num()));
         if !input_path.exists() {
@@ -24,12 +35,15 @@ pub trait Day {
             input_path.push(format!("d{}", Self::day()));
         }
 
-        let input_file = File::open(input_path).map_err(|err| InputError::from(err))?;
-        let reader = BufReader::new(input_file);
+        let input_file = File::open(input_path).map_err(InputError::from)?;
+        Ok(BufReader::new(input_file))
+    }
 
+    /// Run the solution for the given daily `part` over an input buffer
+    fn run_with<'a>(part: Part, reader: impl BufRead + 'a, params: &Params) -> Result<String> {
         match part {
-            Part::One => Self::P1::run(reader),
-            Part::Two => Self::P2::run(reader),
+            Part::One => <Self::P1<'a> as Solution<'a>>::run(reader, params),
+            Part::Two => <Self::P2<'a> as Solution<'a>>::run(reader, params),
         }
     }
 }
@@ -0,0 +1,34 @@
+/// Assert that a day's part produces `expected` for an inline puzzle input,
+/// e.g. the puzzle's worked example:
+///
+/// ```ignore
+/// day_test!(D7, Part::Two, "190: 10 19\n3267: 81 40 27\n...", 11387);
+/// ```
+///
+/// Puzzle parameters which differ for the example can follow the expected
+/// answer:
+///
+/// ```ignore
+/// day_test!(D14, Part::One, EXAMPLE, 12, width = 11, height = 7);
+/// ```
+///
+/// The input is run through [`Day::run_with`](crate::harness::Day::run_with),
+/// so it's parsed exactly as the real input file would be.
+#[macro_export]
+macro_rules! day_test {
+    ($day:ty, $part:expr, $input:expr, $expected:expr $(, $name:ident = $value:expr)* $(,)?) => {{
+        let part: $crate::harness::Part = $part;
+        #[allow(unused_mut)]
+        let mut params = $crate::harness::Params::new();
+        $(params.set(stringify!($name), $value.to_string());)*
+        let output = <$day as $crate::harness::Day>::run_with(part, $input.as_bytes(), &params)
+            .unwrap_or_else(|err| panic!("{} {:?} failed: {}", stringify!($day), part, err));
+        assert_eq!(
+            $expected.to_string(),
+            output,
+            "{} {:?}",
+            stringify!($day),
+            part
+        );
+    }};
+}
@@ -18,6 +18,26 @@ pub enum InputError {
         msg: String,
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
+
+    #[error("line {line}: {source}")]
+    AtLine {
+        line: usize,
+        #[source]
+        source: Box<InputError>,
+    },
+}
+
+impl InputError {
+    /// Attach the (1-indexed) line number the error occurred on
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            Self::AtLine { .. } => self,
+            err => Self::AtLine {
+                line,
+                source: Box::new(err),
+            },
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, InputError>;
@@ -1,9 +1,10 @@
 //! Utilities for lazy iterator-based solutions.
 //!
 //! Solutions which don't require random access to the entire input can instead
-//! iterate over inputs in chunks, e.g. line by line ([`lines`]).
+//! iterate over inputs in chunks, e.g. line by line ([`lines`]) or blank-line
+//! delimited block by block ([`sections`], [`records`]).
 
-use std::io::BufRead;
+use std::{io::BufRead, iter::Sum};
 
 use super::{input, solution};
 
@@ -15,6 +16,90 @@ pub trait Inputs<Item>: Iterator<Item = input::Result<Item>> {
     {
         std::iter::Iterator::fold(self, Ok(init), |acc, cur| f(acc?, cur?))
     }
+
+    /// Sum the inputs, stopping at the first error
+    fn try_sum<S>(self) -> solution::Result<S>
+    where
+        Self: Sized,
+        S: Sum<Item>,
+    {
+        Ok(self.sum::<input::Result<S>>()?)
+    }
+
+    /// Count the inputs matching `pred`, stopping at the first error
+    fn try_count_where<P>(self, mut pred: P) -> solution::Result<usize>
+    where
+        Self: Sized,
+        P: FnMut(&Item) -> bool,
+    {
+        self.fold_solve(0, |count, item| {
+            Ok(if pred(&item) { count + 1 } else { count })
+        })
+    }
+
+    /// Collect the inputs, stopping at the first error
+    fn try_collect<C>(self) -> solution::Result<C>
+    where
+        Self: Sized,
+        C: FromIterator<Item>,
+    {
+        Ok(self.collect::<input::Result<C>>()?)
+    }
+
+    /// Lazily map each input with a fallible `f`
+    fn try_map<U, F>(self, mut f: F) -> impl Inputs<U>
+    where
+        Self: Sized,
+        F: FnMut(Item) -> input::Result<U>,
+    {
+        self.map(move |item| item.and_then(&mut f))
+    }
+
+    /// Pair each input with its line number (1-indexed), attaching the line
+    /// number to any error already in the stream. Only meaningful for
+    /// line-based inputs, e.g. [`lines`].
+    fn enumerate_lines(self) -> impl Inputs<(usize, Item)>
+    where
+        Self: Sized,
+    {
+        self.enumerate().map(|(idx, item)| {
+            item.map(|item| (idx + 1, item))
+                .map_err(|err| err.at_line(idx + 1))
+        })
+    }
+
+    /// Group the inputs into `Vec`s of `size` items (the last group may be
+    /// smaller). An error is yielded in place of the group it occurs in, and
+    /// ends the iterator, as any later groups would be misaligned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    fn chunks(mut self, size: usize) -> impl Inputs<Vec<Item>>
+    where
+        Self: Sized,
+    {
+        assert!(size > 0, "chunk size must be non-zero");
+
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let mut chunk = Vec::with_capacity(size);
+            while chunk.len() < size {
+                match self.next() {
+                    Some(Ok(item)) => chunk.push(item),
+                    Some(Err(err)) => {
+                        failed = true;
+                        return Some(Err(err));
+                    }
+                    None => break,
+                }
+            }
+            (!chunk.is_empty()).then_some(Ok(chunk))
+        })
+    }
 }
 
 impl<T, Item> Inputs<Item> for T where T: Iterator<Item = input::Result<Item>> {}
@@ -25,3 +110,155 @@ pub fn lines<R: BufRead>(reader: R) -> impl Inputs<String> {
         .lines()
         .map(|line| line.map_err(input::InputError::from))
 }
+
+/// Block of consecutive non-blank lines
+#[derive(Debug, PartialEq)]
+pub struct Section {
+    /// Line number of the section's first line (1-indexed)
+    pub line_num: usize,
+    pub lines: Vec<String>,
+}
+
+/// Iterator over the blank-line delimited sections of the input. Runs of blank
+/// (or whitespace-only) lines are treated as a single delimiter, and leading or
+/// trailing blank lines never produce empty sections.
+pub fn sections<R: BufRead>(reader: R) -> impl Inputs<Section> {
+    let mut lines = lines(reader).enumerate();
+
+    std::iter::from_fn(move || {
+        let mut section: Option<Section> = None;
+
+        for (idx, line) in lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if line.trim().is_empty() {
+                if section.is_some() {
+                    break;
+                }
+                continue;
+            }
+
+            section
+                .get_or_insert_with(|| Section {
+                    line_num: idx + 1,
+                    lines: vec![],
+                })
+                .lines
+                .push(line);
+        }
+
+        section.map(Ok)
+    })
+}
+
+/// Iterator over the blank-line delimited records (paragraphs) of the input,
+/// i.e. [`sections`] without line numbers
+pub fn records<R: BufRead>(reader: R) -> impl Inputs<Vec<String>> {
+    sections(reader).map(|section| section.map(|s| s.lines))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::harness::{input, InputError};
+
+    use super::{lines, records, sections, Inputs, Section};
+
+    fn parse_nums(input: &str) -> impl Inputs<u32> + '_ {
+        lines(input.as_bytes())
+            .enumerate_lines()
+            .try_map(|(line_num, line)| {
+                crate::parse::parse_u32(&line).map_err(|err| err.at_line(line_num))
+            })
+    }
+
+    #[test]
+    fn test_combinators() {
+        let input = "1\n2\n3\n4\n5";
+
+        assert_eq!(15, parse_nums(input).try_sum::<u32>().unwrap());
+        assert_eq!(
+            2,
+            parse_nums(input).try_count_where(|n| n % 2 == 0).unwrap()
+        );
+        assert_eq!(
+            vec![1, 2, 3, 4, 5],
+            parse_nums(input).try_collect::<Vec<_>>().unwrap()
+        );
+        assert_eq!(
+            vec![vec![1, 2], vec![3, 4], vec![5]],
+            parse_nums(input).chunks(2).try_collect::<Vec<_>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_error_line_context() {
+        let input = "1\n2\nthree\n4";
+
+        let err = parse_nums(input).try_sum::<u32>().unwrap_err();
+        assert_eq!(
+            "line 3: failed to parse input: Failed to parse three",
+            err.to_string()
+        );
+
+        let results: Vec<input::Result<Vec<u32>>> = parse_nums(input).chunks(3).collect();
+        assert_eq!(1, results.len());
+        assert!(matches!(
+            results[0],
+            Err(InputError::AtLine { line: 3, .. })
+        ));
+
+        // groups before the error are still yielded
+        let results: Vec<input::Result<Vec<u32>>> = parse_nums(input).chunks(2).collect();
+        assert_eq!(2, results.len());
+        assert_eq!(vec![1, 2], *results[0].as_ref().unwrap());
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn test_sections() {
+        let input = "\na\nb\n\n\n  \nc\n\nd\ne\n\n";
+
+        let sections: Vec<_> = sections(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                Section {
+                    line_num: 2,
+                    lines: vec!["a".into(), "b".into()],
+                },
+                Section {
+                    line_num: 7,
+                    lines: vec!["c".into()],
+                },
+                Section {
+                    line_num: 9,
+                    lines: vec!["d".into(), "e".into()],
+                },
+            ],
+            sections
+        );
+    }
+
+    #[test]
+    fn test_records() {
+        let input = "1|2\n3|4\n\n1,2,3";
+
+        let records: Vec<_> = records(input.as_bytes()).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(
+            vec![vec!["1|2".to_string(), "3|4".into()], vec!["1,2,3".into()]],
+            records
+        );
+    }
+
+    #[test]
+    fn test_no_sections() {
+        assert_eq!(0, sections("".as_bytes()).count());
+        assert_eq!(0, sections("\n \n\n".as_bytes()).count());
+    }
+}
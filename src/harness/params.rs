@@ -0,0 +1,136 @@
+//! Puzzle parameters which can be overridden at runtime.
+//!
+//! Some puzzles bake in constants (grid sizes, step counts, limits) which
+//! differ between the worked example and the real input. Solutions declare
+//! those as typed [`Param`]s with the real puzzle's value as the default, and
+//! read them from the [`Params`] they're run with, e.g. from the command line:
+//!
+//! ```text
+//! $ cargo run 11 --param blinks=40
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+/// Named, typed puzzle parameter with a default value
+#[derive(Debug)]
+pub struct Param<T> {
+    pub name: &'static str,
+    pub default: T,
+}
+
+impl<T> Param<T> {
+    pub const fn new(name: &'static str, default: T) -> Self {
+        Self { name, default }
+    }
+}
+
+/// Parameter overrides, keyed by name
+#[derive(Debug, Default)]
+pub struct Params {
+    values: BTreeMap<String, String>,
+    used: RefCell<BTreeSet<String>>,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    /// Value of `param`, or its default when it hasn't been overridden
+    pub fn get<T>(&self, param: &Param<T>) -> Result<T, ParamError>
+    where
+        T: FromStr + Clone,
+    {
+        let value = match self.values.get(param.name) {
+            Some(value) => value,
+            None => return Ok(param.default.clone()),
+        };
+        self.used.borrow_mut().insert(param.name.to_string());
+
+        value.parse().map_err(|_| ParamError::InvalidValue {
+            name: param.name.to_string(),
+            value: value.clone(),
+        })
+    }
+
+    /// Names of overridden parameters which nothing has read, e.g. typos
+    pub fn unused(&self) -> Vec<&str> {
+        let used = self.used.borrow();
+        self.values
+            .keys()
+            .filter(|name| !used.contains(*name))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl FromIterator<(String, String)> for Params {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut params = Params::new();
+        for (name, value) in iter {
+            params.set(name, value);
+        }
+        params
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParamError {
+    #[error("invalid value for parameter '{name}': '{value}'")]
+    InvalidValue { name: String, value: String },
+
+    #[error("expected a parameter of the form NAME=VALUE, but got: '{0}'")]
+    Malformed(String),
+}
+
+/// Parse a `NAME=VALUE` parameter override
+pub fn parse_override(s: &str) -> Result<(String, String), ParamError> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(ParamError::Malformed(s.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_override, Param, Params};
+
+    const BLINKS: Param<usize> = Param::new("blinks", 25);
+    const WIDTH: Param<usize> = Param::new("width", 101);
+
+    #[test]
+    fn test_get() {
+        let params: Params = [parse_override("blinks=40").unwrap()].into_iter().collect();
+
+        assert_eq!(40, params.get(&BLINKS).unwrap());
+        assert_eq!(101, params.get(&WIDTH).unwrap());
+        assert!(params.unused().is_empty());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut params = Params::new();
+        params.set("blinks", "many");
+        params.set("blink", "40");
+
+        assert_eq!(
+            "invalid value for parameter 'blinks': 'many'",
+            params.get(&BLINKS).unwrap_err().to_string()
+        );
+        assert_eq!(vec!["blink"], params.unused());
+        assert!(parse_override("blinks").is_err());
+        assert!(parse_override("=40").is_err());
+    }
+}
@@ -2,7 +2,10 @@ use std::{fmt::Display, io::BufRead};
 
 use thiserror::Error;
 
-use super::input::{InputError, SolutionInput};
+use super::{
+    input::{InputError, SolutionInput},
+    params::{ParamError, Params},
+};
 
 /// Solution for a given daily exercise's part component
 pub trait Solution<'a> {
@@ -14,10 +17,17 @@ pub trait Solution<'a> {
     /// Compute the solution from the pre-processed input
     fn solve(input: Self::Input) -> Result<Self::Output>;
 
+    /// Compute the solution from the pre-processed input and puzzle
+    /// parameters. Solutions with [`Param`](super::Param)s override this, and
+    /// implement [`Solution::solve`] with the default parameters.
+    fn solve_with(input: Self::Input, _params: &Params) -> Result<Self::Output> {
+        Self::solve(input)
+    }
+
     /// Run the solution over an input buffer
-    fn run(reader: impl BufRead + 'a) -> Result<String> {
+    fn run(reader: impl BufRead + 'a, params: &Params) -> Result<String> {
         let input = Self::Input::read(reader)?;
-        let output = Self::solve(input)?;
+        let output = Self::solve_with(input, params)?;
         Ok(format!("{}", output))
     }
 }
@@ -31,6 +41,9 @@ pub enum Error {
     #[error(transparent)]
     FileInput(#[from] InputError),
 
+    #[error(transparent)]
+    Param(#[from] ParamError),
+
     #[error(transparent)]
     SolutionError(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
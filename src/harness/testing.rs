@@ -0,0 +1,219 @@
+//! Property-based test support.
+//!
+//! Generators produce random puzzle-shaped inputs (as the text a
+//! [`SolutionInput`](super::SolutionInput) would read), and [`check`] runs a
+//! property over many generated cases, reporting the seed and input of the
+//! first failure so it can be replayed.
+//!
+//! Cases are deterministic by default. Set `PROP_SEED` to explore other inputs
+//! (or replay a reported failure) and `PROP_CASES` to change the case count.
+
+use std::{env, fmt::Debug};
+
+const DEFAULT_SEED: u64 = 0x00a0_c202_4000_0001;
+const DEFAULT_CASES: usize = 200;
+
+/// Small, fast, seedable PRNG (SplitMix64)
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `lo..=hi`
+    pub fn range(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo <= hi, "empty range {}..={}", lo, hi);
+        match (hi - lo).checked_add(1) {
+            Some(span) => lo + self.next_u64() % span,
+            None => self.next_u64(),
+        }
+    }
+
+    /// Uniform index in `0..len`
+    pub fn index(&mut self, len: usize) -> usize {
+        self.range(0, len as u64 - 1) as usize
+    }
+
+    pub fn chance(&mut self, numerator: u64, denominator: u64) -> bool {
+        self.range(1, denominator) <= numerator
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.index(items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.index(i + 1));
+        }
+    }
+}
+
+/// Run `prop` over `PROP_CASES` inputs built by `gen`, panicking with the
+/// seed and input of the first case that doesn't hold
+pub fn check<T, G, P>(name: &str, mut gen: G, mut prop: P)
+where
+    T: Debug,
+    G: FnMut(&mut Rng) -> T,
+    P: FnMut(&T) -> bool,
+{
+    let seed = env_or("PROP_SEED", DEFAULT_SEED);
+    let cases = env_or("PROP_CASES", DEFAULT_CASES as u64);
+
+    for case in 0..cases {
+        let case_seed = seed.wrapping_add(case);
+        let input = gen(&mut Rng::new(case_seed));
+        if !prop(&input) {
+            panic!(
+                "property '{}' failed (PROP_SEED={} PROP_CASES=1) for input:\n{:#?}",
+                name, case_seed, input
+            );
+        }
+    }
+}
+
+fn env_or(var: &str, default: u64) -> u64 {
+    env::var(var)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Grid of random chars from `alphabet`, one row per line (e.g. day 12 plots)
+pub fn char_grid(rng: &mut Rng, rows: usize, cols: usize, alphabet: &[char]) -> String {
+    let mut grid = String::with_capacity(rows * (cols + 1));
+    for _ in 0..rows {
+        for _ in 0..cols {
+            grid.push(*rng.pick(alphabet));
+        }
+        grid.push('\n');
+    }
+    grid
+}
+
+/// Day 10 style trail map: a random walk of heights so that trails are
+/// common, rather than uniform noise where they almost never occur
+pub fn trail_grid(rng: &mut Rng, rows: usize, cols: usize) -> String {
+    let mut heights = vec![vec![0_u64; cols]; rows];
+    for row in 0..rows {
+        for col in 0..cols {
+            let base = match (row, col) {
+                (0, 0) => rng.range(0, 9),
+                (0, _) => heights[row][col - 1],
+                (_, _) => heights[row - 1][col],
+            };
+            heights[row][col] = match rng.range(0, 3) {
+                0 => base.saturating_sub(1),
+                1 => (base + 1).min(9),
+                _ => rng.range(0, 9),
+            };
+        }
+    }
+
+    let mut grid = String::new();
+    for row in heights {
+        for h in row {
+            grid.push(char::from_digit(h as u32, 10).unwrap());
+        }
+        grid.push('\n');
+    }
+    grid
+}
+
+/// Day 6 style lab map: scattered `#` obstacles and a guard (`^`, `>`, `v` or
+/// `<`) somewhere on the map
+pub fn guard_map(rng: &mut Rng, rows: usize, cols: usize) -> String {
+    let mut tiles: Vec<Vec<char>> = (0..rows)
+        .map(|_| {
+            (0..cols)
+                .map(|_| if rng.chance(1, 6) { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    tiles[rng.index(rows)][rng.index(cols)] = *rng.pick(&['^', '>', 'v', '<']);
+
+    let mut map = String::new();
+    for row in tiles {
+        map.extend(row);
+        map.push('\n');
+    }
+    map
+}
+
+/// Day 9 style dense disk map of `len` digits, alternating file and free
+/// span sizes in `0..=9`
+pub fn disk_map(rng: &mut Rng, len: usize) -> String {
+    (0..len)
+        .map(|_| char::from_digit(rng.range(0, 9) as u32, 10).unwrap())
+        .collect()
+}
+
+/// Day 7 style calibration equations. Roughly half of the targets are
+/// reachable by construction (with `+`, `*` and, if `use_concat`, `||`), the
+/// rest are random.
+pub fn equations(rng: &mut Rng, count: usize, max_terms: usize, use_concat: bool) -> String {
+    let mut lines = String::new();
+
+    for _ in 0..count {
+        let n_terms = rng.range(1, max_terms as u64) as usize;
+        let terms: Vec<u64> = (0..n_terms).map(|_| rng.range(1, 99)).collect();
+
+        let target = if rng.chance(1, 2) {
+            terms[1..].iter().fold(terms[0], |acc, term| {
+                let ops = if use_concat { 3 } else { 2 };
+                match rng.range(0, ops - 1) {
+                    0 => acc + term,
+                    1 => acc * term,
+                    _ => format!("{}{}", acc, term).parse().unwrap(),
+                }
+            })
+        } else {
+            rng.range(1, 10_u64.pow(n_terms.min(9) as u32))
+        };
+
+        let terms: Vec<String> = terms.iter().map(u64::to_string).collect();
+        lines.push_str(&format!("{}: {}\n", target, terms.join(" ")));
+    }
+
+    lines
+}
+
+/// Day 5 style page ordering rules and updates. Rules are drawn from a hidden
+/// total order of `pages` distinct page numbers below `max_page`, so they never
+/// form a cycle.
+pub fn page_ordering(rng: &mut Rng, pages: usize, updates: usize, max_page: u32) -> String {
+    let mut order: Vec<u32> = (0..max_page).collect();
+    rng.shuffle(&mut order);
+    order.truncate(pages);
+
+    let mut input = String::new();
+    for i in 0..order.len() {
+        for j in i + 1..order.len() {
+            if rng.chance(2, 3) {
+                input.push_str(&format!("{}|{}\n", order[i], order[j]));
+            }
+        }
+    }
+    input.push('\n');
+
+    for _ in 0..updates {
+        let mut update = order.clone();
+        rng.shuffle(&mut update);
+        update.truncate(rng.range(1, pages as u64) as usize);
+        let update: Vec<String> = update.iter().map(u32::to_string).collect();
+        input.push_str(&update.join(","));
+        input.push('\n');
+    }
+
+    input
+}
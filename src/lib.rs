@@ -2,3 +2,5 @@ pub mod cli;
 pub mod day;
 pub mod harness;
 pub mod parse;
+pub mod render;
+pub mod scaffold;
@@ -1,7 +1,14 @@
+use std::{
+    env::current_dir,
+    fs::File,
+    io::{self, stdin, BufReader, BufWriter},
+};
+
 use aoc24::{
-    cli::Cli,
-    day::*,
-    harness::{Day, Part},
+    cli::{Cli, Command, Inspect},
+    day::{d1, d14, d2, d3, d4, d5, d6, d7, d9, *},
+    harness::{Day, Inputs, Params, Part, SolutionInput},
+    scaffold,
 };
 
 use anyhow::{anyhow, Result};
@@ -9,27 +16,283 @@ use clap::Parser;
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    // `cli.part` guaranteed to be 1 or 2 via `clap`
-    let part = Part::try_from(cli.part).expect("`clap` to parse valid part");
-
-    let solution = match cli.day {
-        1 => D1::run(part),
-        2 => D2::run(part),
-        3 => D3::run(part),
-        4 => D4::run(part),
-        5 => D5::run(part),
-        6 => D6::run(part),
-        7 => D7::run(part),
-        8 => D8::run(part),
-        9 => D9::run(part),
-        10 => D10::run(part),
-        11 => D11::run(part),
-        12 => D12::run(part),
-        13 => D13::run(part),
-        14 => D14::run(part),
-        _ => return Err(anyhow!("Day {} not yet solved", cli.day)),
+
+    match cli.command {
+        Some(Command::New { day }) => new_day(day),
+        Some(Command::Inspect(inspect)) => run_inspect(inspect),
+        // `cli.day` guaranteed to be present via `clap` when there's no subcommand
+        None => run_day(
+            cli.day.expect("`clap` to require a day"),
+            cli.part,
+            cli.params.params.into_iter().collect(),
+        ),
+    }
+}
+
+fn run_day(day: u8, part: u8, params: Params) -> Result<()> {
+    // `part` guaranteed to be 1 or 2 via `clap`
+    let part = Part::try_from(part).expect("`clap` to parse valid part");
+
+    let solution = match day {
+        1 => D1::run(part, &params),
+        2 => D2::run(part, &params),
+        3 => D3::run(part, &params),
+        4 => D4::run(part, &params),
+        5 => D5::run(part, &params),
+        6 => D6::run(part, &params),
+        7 => D7::run(part, &params),
+        8 => D8::run(part, &params),
+        9 => D9::run(part, &params),
+        10 => D10::run(part, &params),
+        11 => D11::run(part, &params),
+        12 => D12::run(part, &params),
+        13 => D13::run(part, &params),
+        14 => D14::run(part, &params),
+        _ => return Err(anyhow!("Day {} not yet solved", day)),
     }?;
 
-    println!("Day {} part {} solution: {}", cli.day, cli.part, solution);
+    warn_unused(&params);
+
+    println!("Day {} part {} solution: {}", day, part.num(), solution);
     Ok(())
 }
+
+fn new_day(day: u8) -> Result<()> {
+    let created = scaffold::new_day(&current_dir()?, day)?;
+    println!("Created {}", created.display());
+    Ok(())
+}
+
+fn run_inspect(inspect: Inspect) -> Result<()> {
+    match inspect {
+        Inspect::D1 { buckets, csv } => {
+            let input = d1::p1::Input::read(D1::input(Part::One)?)?;
+            let distances = d1::analysis::distances(&input);
+
+            println!(
+                "{} pairs, total distance {}",
+                distances.len(),
+                distances.iter().map(|d| *d as u64).sum::<u64>()
+            );
+            if let Some(stats) = d1::analysis::stats(&distances) {
+                println!(
+                    "distance min {}, median {}, max {}",
+                    stats.min, stats.median, stats.max
+                );
+            }
+
+            let histogram = d1::analysis::histogram(&distances, buckets);
+            print!("{}", d1::analysis::render_histogram(&histogram, 40));
+
+            let (only_left, only_right) = d1::analysis::unmatched(&input);
+            println!(
+                "only in left list ({}): {}",
+                only_left.len(),
+                preview(&only_left)
+            );
+            println!(
+                "only in right list ({}): {}",
+                only_right.len(),
+                preview(&only_right)
+            );
+
+            if let Some(path) = csv {
+                d1::analysis::write_csv(&input, BufWriter::new(File::create(&path)?))?;
+                println!("Wrote {}", path.display());
+            }
+        }
+        Inspect::D2 { tolerance } => {
+            let reports: Box<dyn Inputs<d2::Report>> = SolutionInput::read(D2::input(Part::One)?)?;
+            let (mut safe, mut fixable, mut unsafe_) = (0, 0, 0);
+            for report in reports {
+                let report = report?;
+                let diagnosis = report.diagnose(tolerance);
+                match diagnosis {
+                    d2::Diagnosis::Safe => safe += 1,
+                    d2::Diagnosis::Fixable(_) => fixable += 1,
+                    d2::Diagnosis::Unsafe { .. } => unsafe_ += 1,
+                }
+                let levels: Vec<String> = report.levels().iter().map(u8::to_string).collect();
+                println!("{}: {}", levels.join(" "), diagnosis);
+            }
+            println!("{} safe, {} fixable, {} unsafe", safe, fixable, unsafe_);
+        }
+        Inspect::D3 { part } => {
+            let part = Part::try_from(part).expect("`clap` to parse valid part");
+            let instructions: Box<dyn Inputs<d3::Instruction>> =
+                SolutionInput::read(D3::input(part)?)?;
+            let instructions: Vec<d3::Instruction> = instructions.try_collect()?;
+            let mut machine = d3::Machine::new(part);
+            for (instruction, applied) in machine.trace(instructions)? {
+                println!(
+                    "{:>8}  {:<16} {}",
+                    instruction.offset,
+                    instruction.to_string(),
+                    if applied { "applied" } else { "ignored" }
+                );
+            }
+            println!("result: {}", machine.count());
+        }
+        Inspect::D4 { words, template } => {
+            let crossword = d4::Crossword::read(D4::input(Part::One)?)?;
+
+            match template {
+                Some(template) => {
+                    let matches = crossword.find_template(&template);
+                    let found = matches.iter().flat_map(d4::TemplateMatch::points);
+                    print!("{}", crossword.render(found, true));
+                    for found in &matches {
+                        println!("{}", found);
+                    }
+                    println!("{}: {} found", template, matches.len());
+                }
+                None => {
+                    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+                    let matches = crossword.search(&words);
+                    let found = matches.iter().flat_map(d4::WordMatch::points);
+                    print!("{}", crossword.render(found, true));
+                    for found in &matches {
+                        println!("{}", found);
+                    }
+                    for word in words {
+                        let count = matches.iter().filter(|m| m.word == word).count();
+                        println!("{}: {} found", word, count);
+                    }
+                }
+            }
+        }
+        Inspect::D5 => {
+            let input = d5::Input::read(D5::input(Part::One)?)?;
+            let (mut reordered, mut ambiguous) = (0, 0);
+            for diagnosis in input.diagnose() {
+                if let d5::Order::Reordered(order) = &diagnosis.order {
+                    reordered += 1;
+                    ambiguous += order.ambiguous.is_some() as usize;
+                }
+                println!("{}", diagnosis);
+            }
+            println!(
+                "ambiguous: {} of {} reordered updates have more than one valid order",
+                ambiguous, reordered
+            );
+        }
+        Inspect::D6 {
+            obstacle,
+            ppm,
+            scale,
+        } => {
+            let map = d6::Map::read(D6::input(Part::Two)?)?;
+            let route = d6::route::Route::trace(map, obstacle);
+
+            match ppm {
+                Some(path) => {
+                    route
+                        .render_image(scale)
+                        .write_ppm(BufWriter::new(File::create(&path)?))?;
+                    println!("Wrote {}", path.display());
+                }
+                None => print!("{}", route.render_text(true)),
+            }
+            if obstacle.is_some() && route.obstacle().is_none() {
+                eprintln!("warning: couldn't place an obstacle there");
+            }
+            println!("{}", route);
+        }
+        Inspect::D7 { ops, count } => {
+            let equations: Box<dyn Inputs<d7::Equation>> =
+                SolutionInput::read(D7::input(Part::One)?)?;
+            let mut total = 0;
+            for eq in equations {
+                let eq = eq?;
+                match eq.solve(ops) {
+                    Some(ops) => {
+                        total += eq.target();
+                        print!("{}", eq.with_ops(&ops));
+                    }
+                    None => print!("{} (no solution)", eq),
+                }
+                if count {
+                    match eq.count_solutions(ops) {
+                        Some(ways) => {
+                            print!(" [{} way{}]", ways, if ways == 1 { "" } else { "s" })
+                        }
+                        None => print!(" [too many ways to count]"),
+                    }
+                }
+                println!();
+            }
+            println!("total calibration result: {}", total);
+        }
+        Inspect::D9 { part, max_blocks } => {
+            let part = Part::try_from(part).expect("`clap` to parse valid part");
+            let mut disk = d9::Disk::read(D9::input(part)?)?;
+            let show_moves = disk.len() <= max_blocks;
+
+            if show_moves {
+                println!("{}", d9::trace::render(&disk));
+            }
+            let summary = d9::trace::compact(&mut disk, part, |mv, disk| {
+                if show_moves {
+                    println!(
+                        "{}  file {} moved {} block(s) from {} to {}",
+                        d9::trace::render(disk),
+                        mv.file_id,
+                        mv.len,
+                        mv.from,
+                        mv.to
+                    );
+                }
+            });
+            println!("{}", summary);
+        }
+        Inspect::D14 {
+            from,
+            to,
+            pgm,
+            scale,
+            fps,
+            tree,
+            params,
+        } => {
+            let params: Params = params.params.into_iter().collect();
+            let mut bathroom = d14::Bathroom::read(D14::input(Part::One)?)?;
+            bathroom.resize(&params)?;
+
+            match pgm {
+                _ if tree => {
+                    let second = bathroom.find_tree();
+                    println!("Christmas tree after {} seconds:", second);
+                    print!("{:?}", bathroom);
+                }
+                Some(dir) => {
+                    let frames = d14::anim::export_frames(&mut bathroom, from..to, &dir, scale)?;
+                    println!("Wrote {} frames to {}", frames.len(), dir.display());
+                }
+                None => {
+                    let controls = d14::anim::controls(BufReader::new(stdin()));
+                    d14::anim::play(&mut bathroom, from..to, fps, io::stdout(), &controls)?;
+                }
+            }
+            warn_unused(&params);
+        }
+    }
+    Ok(())
+}
+
+/// Up to the first 10 of `ids`, comma separated
+fn preview(ids: &[u32]) -> String {
+    let shown: Vec<String> = ids.iter().take(10).map(u32::to_string).collect();
+    match ids.len() {
+        0 => "none".to_string(),
+        len if len > shown.len() => {
+            format!("{}, ... ({} more)", shown.join(", "), len - shown.len())
+        }
+        _ => shown.join(", "),
+    }
+}
+
+fn warn_unused(params: &Params) {
+    for name in params.unused() {
+        eprintln!("warning: parameter '{}' is not used by this solution", name);
+    }
+}
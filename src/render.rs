@@ -0,0 +1,134 @@
+//! Rendering helpers for visualising puzzle state, as images (binary
+//! [Netpbm](https://netpbm.sourceforge.net/doc/) PPM/PGM, readable by most
+//! image tools) or in the terminal with ANSI escape codes.
+
+use std::io::{self, Write};
+
+/// 8-bit RGB color
+pub type Rgb = [u8; 3];
+
+pub const BLACK: Rgb = [0, 0, 0];
+pub const WHITE: Rgb = [255, 255, 255];
+
+/// RGB raster image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, fill: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgb> {
+        (x < self.width && y < self.height).then(|| self.pixels[y * self.width + x])
+    }
+
+    /// Set a pixel, ignoring coordinates outside the image
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    /// Fill the `scale`x`scale` block of pixels for grid cell (`col`, `row`)
+    pub fn fill_cell(&mut self, col: usize, row: usize, scale: usize, color: Rgb) {
+        for y in row * scale..(row + 1) * scale {
+            for x in col * scale..(col + 1) * scale {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    /// Write as a binary PPM (`P6`)
+    pub fn write_ppm(&self, mut w: impl Write) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for pixel in &self.pixels {
+            w.write_all(pixel)?;
+        }
+        w.flush()
+    }
+
+    /// Write as a binary PGM (`P5`), converting colors to luma
+    pub fn write_pgm(&self, mut w: impl Write) -> io::Result<()> {
+        write!(w, "P5\n{} {}\n255\n", self.width, self.height)?;
+        let luma: Vec<u8> = self.pixels.iter().map(|p| luma(*p)).collect();
+        w.write_all(&luma)?;
+        w.flush()
+    }
+}
+
+/// Rec. 601 luma of a color
+pub fn luma([r, g, b]: Rgb) -> u8 {
+    ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+}
+
+/// ANSI terminal escape codes
+pub mod ansi {
+    use super::Rgb;
+
+    /// Clear the screen and move the cursor to the top left
+    pub const CLEAR: &str = "\x1b[2J\x1b[H";
+    pub const RESET: &str = "\x1b[0m";
+    pub const BOLD: &str = "\x1b[1m";
+    pub const DIM: &str = "\x1b[2m";
+
+    /// 24-bit foreground color
+    pub fn fg([r, g, b]: Rgb) -> String {
+        format!("\x1b[38;2;{};{};{}m", r, g, b)
+    }
+
+    /// 24-bit background color
+    pub fn bg([r, g, b]: Rgb) -> String {
+        format!("\x1b[48;2;{};{};{}m", r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Image, BLACK, WHITE};
+
+    #[test]
+    fn test_netpbm() {
+        let mut image = Image::new(2, 2, BLACK);
+        image.fill_cell(1, 0, 1, WHITE);
+        image.set(5, 5, WHITE);
+
+        let mut ppm = vec![];
+        image.write_ppm(&mut ppm).unwrap();
+        assert_eq!(
+            b"P6\n2 2\n255\n\0\0\0\xff\xff\xff\0\0\0\0\0\0".to_vec(),
+            ppm
+        );
+
+        let mut pgm = vec![];
+        image.write_pgm(&mut pgm).unwrap();
+        assert_eq!(b"P5\n2 2\n255\n\0\xff\0\0".to_vec(), pgm);
+    }
+
+    #[test]
+    fn test_fill_cell() {
+        let mut image = Image::new(4, 4, BLACK);
+        image.fill_cell(1, 1, 2, WHITE);
+
+        assert_eq!(Some(WHITE), image.get(2, 2));
+        assert_eq!(Some(WHITE), image.get(3, 3));
+        assert_eq!(Some(BLACK), image.get(1, 1));
+        assert_eq!(None, image.get(4, 0));
+    }
+}
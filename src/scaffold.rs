@@ -0,0 +1,288 @@
+//! Code generation for starting a new day's solution.
+//!
+//! [`new_day`] writes a skeleton `src/day/dN.rs` from [`TEMPLATE`] and
+//! registers it in `src/day.rs` (module + re-export) and `src/main.rs` (the
+//! day's match arm).
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use thiserror::Error;
+
+/// Skeleton solution, with `{day}` standing in for the day number
+pub const TEMPLATE: &str = r#"use crate::harness::{iter, Day, Solution, SolutionInput};
+
+pub struct D{day};
+
+impl Day for D{day} {
+    type P1<'a> = P1;
+
+    type P2<'a> = P2;
+
+    fn day() -> u8 {
+        {day}
+    }
+}
+
+pub struct P1;
+
+impl<'a> Solution<'a> for P1 {
+    type Input = Input;
+
+    type Output = usize;
+
+    fn solve(_input: Self::Input) -> crate::harness::Result<Self::Output> {
+        todo!()
+    }
+}
+
+pub struct P2;
+
+impl<'a> Solution<'a> for P2 {
+    type Input = Input;
+
+    type Output = usize;
+
+    fn solve(_input: Self::Input) -> crate::harness::Result<Self::Output> {
+        todo!()
+    }
+}
+
+pub struct Input {
+    pub lines: Vec<String>,
+}
+
+impl<'a> SolutionInput<'a> for Input {
+    fn read(reader: impl std::io::BufRead + 'a) -> crate::harness::input::Result<Self> {
+        Ok(Input {
+            lines: iter::lines(reader).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{day_test, harness::Part};
+
+    use super::D{day};
+
+    const EXAMPLE: &str = "\
+";
+
+    #[test]
+    #[ignore = "puzzle example not filled in yet"]
+    fn test_example() {
+        day_test!(D{day}, Part::One, EXAMPLE, 0);
+        day_test!(D{day}, Part::Two, EXAMPLE, 0);
+    }
+}
+"#;
+
+#[derive(Debug, Error)]
+pub enum ScaffoldError {
+    #[error("failed to read or write source file")]
+    Io(#[from] io::Error),
+
+    #[error("day {0} already exists at {1}")]
+    AlreadyExists(u8, PathBuf),
+
+    #[error("couldn't find where to register day {0} in {1}")]
+    NoRegistrationPoint(u8, PathBuf),
+}
+
+pub type Result<T> = std::result::Result<T, ScaffoldError>;
+
+/// Write and register a skeleton solution for `day` in the crate at `root`,
+/// returning the path of the new day module
+pub fn new_day(root: &Path, day: u8) -> Result<PathBuf> {
+    let src = root.join("src");
+    let day_path = src.join("day").join(format!("d{}.rs", day));
+    if day_path.exists() {
+        return Err(ScaffoldError::AlreadyExists(day, day_path));
+    }
+
+    // Compute every edit before writing anything so a failure leaves the
+    // tree untouched
+    let days_path = src.join("day.rs");
+    let days = register_module(&fs::read_to_string(&days_path)?, day);
+    let main_path = src.join("main.rs");
+    let main = register_solution(&fs::read_to_string(&main_path)?, day)
+        .ok_or_else(|| ScaffoldError::NoRegistrationPoint(day, main_path.clone()))?;
+
+    fs::write(&day_path, render(day))?;
+    fs::write(days_path, days)?;
+    fs::write(main_path, main)?;
+
+    Ok(day_path)
+}
+
+/// Render [`TEMPLATE`] for `day`
+pub fn render(day: u8) -> String {
+    TEMPLATE.replace("{day}", &day.to_string())
+}
+
+/// Add `pub mod dN;` and `pub use dN::DN;` to the contents of `src/day.rs`,
+/// keeping each block in the order `rustfmt` sorts it
+pub fn register_module(days: &str, day: u8) -> String {
+    let mut lines: Vec<String> = days.lines().map(String::from).collect();
+    insert_sorted(&mut lines, "pub mod ", format!("pub mod d{};", day));
+    insert_sorted(&mut lines, "pub use ", format!("pub use d{0}::D{0};", day));
+
+    let mut days = lines.join("\n");
+    days.push('\n');
+    days
+}
+
+/// Add the `N => DN::run(part, &params),` arm to the contents of
+/// `src/main.rs`, before the first day arm for a later day or after the last
+pub fn register_solution(main: &str, day: u8) -> Option<String> {
+    static ARM_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?m)^(?<indent>[ \t]*)(?<day>\d+) => D\d+::run\(part, &params\),$").unwrap()
+    });
+
+    let arms: Vec<_> = ARM_RE.captures_iter(main).collect();
+    let later = arms
+        .iter()
+        .find(|arm| arm["day"].parse::<u8>().is_ok_and(|d| d > day));
+
+    Some(match later {
+        Some(later) => {
+            let start = later.get(0).unwrap().start();
+            let arm = format!(
+                "{}{} => D{}::run(part, &params),\n",
+                &later["indent"], day, day
+            );
+            format!("{}{}{}", &main[..start], arm, &main[start..])
+        }
+        None => {
+            let last = arms.last()?;
+            let end = last.get(0).unwrap().end();
+            let arm = format!(
+                "\n{}{} => D{}::run(part, &params),",
+                &last["indent"], day, day
+            );
+            format!("{}{}{}", &main[..end], arm, &main[end..])
+        }
+    })
+}
+
+fn insert_sorted(lines: &mut Vec<String>, prefix: &str, line: String) {
+    // sort on the module name rather than the whole line, e.g. `d1` < `d10`
+    let module = |line: &str| -> String {
+        line.trim_start_matches(prefix)
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect()
+    };
+
+    let block: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.starts_with(prefix))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let idx = match (block.first(), block.last()) {
+        (Some(first), Some(last)) => (*first..=*last)
+            .find(|idx| module(&lines[*idx]) > module(&line))
+            .unwrap_or(last + 1),
+        _ => {
+            // no block yet, start one after a separating blank line
+            if lines.last().is_some_and(|l| !l.is_empty()) {
+                lines.push(String::new());
+            }
+            lines.len()
+        }
+    };
+
+    lines.insert(idx, line);
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{new_day, register_module, register_solution, render};
+
+    #[test]
+    fn test_render() {
+        let rendered = render(15);
+
+        assert!(rendered.contains("pub struct D15;"));
+        assert!(rendered.contains("impl Day for D15 {"));
+        assert!(rendered.contains("    fn day() -> u8 {\n        15\n    }"));
+        assert!(!rendered.contains("{day}"));
+    }
+
+    #[test]
+    fn test_register_module() {
+        let days = "pub mod d1;\npub mod d10;\npub mod d2;\n\npub use d1::D1;\npub use d10::D10;\npub use d2::D2;\n";
+
+        assert_eq!(
+            "pub mod d1;\npub mod d10;\npub mod d11;\npub mod d2;\n\npub use d1::D1;\npub use d10::D10;\npub use d11::D11;\npub use d2::D2;\n",
+            register_module(days, 11)
+        );
+        assert_eq!(
+            "pub mod d1;\npub mod d10;\npub mod d2;\npub mod d3;\n\npub use d1::D1;\npub use d10::D10;\npub use d2::D2;\npub use d3::D3;\n",
+            register_module(days, 3)
+        );
+        assert_eq!("pub mod d1;\n\npub use d1::D1;\n", register_module("", 1));
+    }
+
+    #[test]
+    fn test_register_solution() {
+        let main = "    match day {\n        1 => D1::run(part, &params),\n        2 => D2::run(part, &params),\n        _ => unreachable!(),\n    }\n";
+
+        assert_eq!(
+            Some("    match day {\n        1 => D1::run(part, &params),\n        2 => D2::run(part, &params),\n        3 => D3::run(part, &params),\n        _ => unreachable!(),\n    }\n".to_string()),
+            register_solution(main, 3)
+        );
+        assert_eq!(None, register_solution("fn main() {}", 3));
+    }
+
+    #[test]
+    fn test_register_solution_in_order() {
+        let main = "    match day {\n        1 => D1::run(part, &params),\n        10 => D10::run(part, &params),\n        _ => unreachable!(),\n    }\n";
+
+        assert_eq!(
+            Some("    match day {\n        1 => D1::run(part, &params),\n        2 => D2::run(part, &params),\n        10 => D10::run(part, &params),\n        _ => unreachable!(),\n    }\n".to_string()),
+            register_solution(main, 2)
+        );
+    }
+
+    #[test]
+    fn test_new_middle_day() {
+        let root = std::env::temp_dir().join(format!("aoc24-scaffold-{}", std::process::id()));
+        fs::create_dir_all(root.join("src").join("day")).unwrap();
+        fs::write(
+            root.join("src").join("day.rs"),
+            "pub mod d1;\npub mod d3;\n\npub use d1::D1;\npub use d3::D3;\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src").join("main.rs"),
+            "    match day {\n        1 => D1::run(part, &params),\n        3 => D3::run(part, &params),\n        _ => unreachable!(),\n    }\n",
+        )
+        .unwrap();
+
+        let created = new_day(&root, 2).unwrap();
+
+        assert_eq!(root.join("src").join("day").join("d2.rs"), created);
+        assert_eq!(render(2), fs::read_to_string(&created).unwrap());
+        assert_eq!(
+            "pub mod d1;\npub mod d2;\npub mod d3;\n\npub use d1::D1;\npub use d2::D2;\npub use d3::D3;\n",
+            fs::read_to_string(root.join("src").join("day.rs")).unwrap()
+        );
+        assert_eq!(
+            "    match day {\n        1 => D1::run(part, &params),\n        2 => D2::run(part, &params),\n        3 => D3::run(part, &params),\n        _ => unreachable!(),\n    }\n",
+            fs::read_to_string(root.join("src").join("main.rs")).unwrap()
+        );
+        assert!(new_day(&root, 2).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}